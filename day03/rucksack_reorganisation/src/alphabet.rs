@@ -0,0 +1,148 @@
+///
+/// Item alphabets, mapping each item type to a bit index and a priority.
+///
+/// The puzzle only uses a-z and A-Z, but the alphabet can be swapped for digits, a custom priority
+/// table read from a file, or an open alphabet that accepts any Unicode character.
+///
+use std::{collections::HashMap, fmt};
+
+use super::get_item_weight;
+
+///
+/// Errors raised when building an alphabet or looking up an item in one.
+///
+/// Lines and columns are numbered from 1.
+///
+#[derive(Debug, PartialEq, Eq)]
+pub enum AlphabetError {
+    UnknownItem { item: char },
+    MalformedEntry { line: usize, entry: String },
+    DuplicateItem { line: usize, item: char },
+}
+
+impl fmt::Display for AlphabetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AlphabetError::UnknownItem { item } => write!(f, "{:?} is not in the item alphabet", item),
+            AlphabetError::MalformedEntry { line, entry } => write!(
+                f, "line {}: expected `<item> <priority>`, found {:?}", line, entry
+            ),
+            AlphabetError::DuplicateItem { line, item } => write!(
+                f, "line {}: {:?} is already in the priority table", line, item
+            ),
+        }
+    }
+}
+
+///
+/// ItemAlphabet, the set of valid item types and their priorities.
+///
+/// Every item is given a dense index, which is the bit it occupies in an ItemSet. Closed
+/// alphabets reject unknown items, while an open alphabet adds them as they are first seen.
+///
+#[derive(Debug, Clone)]
+pub struct ItemAlphabet {
+    items: Vec<char>,
+    priorities: Vec<u64>,
+    ascii: [Option<usize>; 128],
+    others: HashMap<char, usize>,
+    open: bool,
+}
+
+impl ItemAlphabet {
+    ///
+    /// Build a closed alphabet from a list of items and their priorities
+    ///
+    fn from_table<I: IntoIterator<Item = (char, u64)>>(table: I) -> ItemAlphabet {
+        let mut alphabet = ItemAlphabet::unicode();
+        for (item, priority) in table {
+            alphabet.add(item, priority);
+        }
+        alphabet.open = false;
+        alphabet
+    }
+    ///
+    /// The puzzle alphabet, a-z with priorities 1-26 and A-Z with priorities 27-52
+    ///
+    pub fn letters() -> ItemAlphabet {
+        ItemAlphabet::from_table(('a'..='z').chain('A'..='Z').map(|x| (x, get_item_weight(x))))
+    }
+    ///
+    /// Digits 0-9, with priorities 1-10
+    ///
+    pub fn digits() -> ItemAlphabet {
+        ItemAlphabet::from_table(('0'..='9').map(|x| (x, x as u64 - '0' as u64 + 1)))
+    }
+    ///
+    /// An open alphabet accepting any character, prioritised by its Unicode scalar value
+    ///
+    pub fn unicode() -> ItemAlphabet {
+        ItemAlphabet { items: vec![], priorities: vec![], ascii: [None; 128], others: HashMap::new(), open: true }
+    }
+    ///
+    /// Parse a priority table, with one `<item> <priority>` entry per line.
+    ///
+    /// Blank lines and lines starting with `#` are ignored.
+    ///
+    pub fn from_priority_table(data: &str) -> Result<ItemAlphabet, AlphabetError> {
+        let mut table: Vec<(char, u64)> = vec![];
+        for (i, line) in data.lines().enumerate() {
+            let entry = line.trim();
+            if entry.is_empty() || entry.starts_with('#') {
+                continue;
+            }
+            let malformed = || AlphabetError::MalformedEntry { line: i + 1, entry: entry.to_string() };
+            let mut split_data = entry.split_whitespace();
+            let mut item = split_data.next().ok_or_else(malformed)?.chars();
+            let priority = split_data.next().ok_or_else(malformed)?.parse::<u64>().map_err(|_| malformed())?;
+            let item = match (item.next(), item.next(), split_data.next()) {
+                (Some(item), None, None) => item,
+                _ => return Err(malformed()),
+            };
+            if table.iter().any(|(x, _)| *x == item) {
+                return Err(AlphabetError::DuplicateItem { line: i + 1, item });
+            }
+            table.push((item, priority));
+        }
+        Ok(ItemAlphabet::from_table(table))
+    }
+    ///
+    /// Add a new item to the alphabet, returning its index
+    ///
+    fn add(&mut self, item: char, priority: u64) -> usize {
+        let index = self.items.len();
+        self.items.push(item);
+        self.priorities.push(priority);
+        match item.is_ascii() {
+            true => self.ascii[item as usize] = Some(index),
+            false => { self.others.insert(item, index); }
+        }
+        index
+    }
+    ///
+    /// Get the index of an item, adding it first if the alphabet is open
+    ///
+    pub fn index(&mut self, item: char) -> Result<usize, AlphabetError> {
+        let found = match item.is_ascii() {
+            true => self.ascii[item as usize],
+            false => self.others.get(&item).copied(),
+        };
+        match (found, self.open) {
+            (Some(index), _) => Ok(index),
+            (None, true) => Ok(self.add(item, item as u64)),
+            (None, false) => Err(AlphabetError::UnknownItem { item }),
+        }
+    }
+    ///
+    /// Get the item at an index
+    ///
+    pub fn item(&self, index: usize) -> char {
+        self.items[index]
+    }
+    ///
+    /// Get the priority of the item at an index
+    ///
+    pub fn priority(&self, index: usize) -> u64 {
+        self.priorities[index]
+    }
+}
@@ -0,0 +1,136 @@
+///
+/// Benchmark of the bitset item sets against the original string based implementation.
+///
+/// `cargo run --release -- bench` generates 300,000 rucksacks, checks that both implementations
+/// give the same priorities and badges, and prints how long each one took.
+///
+use std::time::Instant;
+
+use super::{check_rucksack_groups, generate_rucksacks, get_item_weight, ItemAlphabet};
+
+const RUCKSACK_COUNT: usize = 300_000;
+const COMPARTMENT_SIZE: usize = 24;
+const ITEMS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+///
+/// Xorshift state used to pick and shuffle items. It starts from a fixed seed, so every run
+/// benchmarks the same rucksacks.
+///
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    ///
+    /// Get the next pseudo random number below the upper bound
+    ///
+    fn below(&mut self, upper: usize) -> usize {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state % upper as u64) as usize
+    }
+    ///
+    /// Shuffle a slice in place
+    ///
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+///
+/// Generate valid puzzle data with a given number of rucksacks.
+///
+/// Each group of three gets a badge plus three disjoint pools of 17 items. Each rucksack shares a
+/// single item between its compartments, so both parts have exactly one answer per line/group.
+///
+fn generate_data(rng: &mut Rng, count: usize) -> String {
+    let mut data = String::new();
+    for _ in 0..count / 3 {
+        let mut items = ITEMS.to_vec();
+        rng.shuffle(&mut items);
+        let badge = items[0];
+        for pool in items[1..].chunks(17) {
+            let shared = pool[0];
+            let mut compartment_a: Vec<u8> = (0..COMPARTMENT_SIZE).map(|_| pool[1 + rng.below(8)]).collect();
+            let mut compartment_b: Vec<u8> = (0..COMPARTMENT_SIZE).map(|_| pool[9 + rng.below(8)]).collect();
+            compartment_a[0] = shared;
+            compartment_a[1] = badge;
+            compartment_b[0] = shared;
+            rng.shuffle(&mut compartment_a);
+            rng.shuffle(&mut compartment_b);
+            data.push_str(std::str::from_utf8(&compartment_a).unwrap());
+            data.push_str(std::str::from_utf8(&compartment_b).unwrap());
+            data.push('\n');
+        }
+    }
+    data
+}
+
+///
+/// The original part 1, sorting and deduping compartment a and searching compartment b.
+///
+fn naive_priorities(data: &str) -> u64 {
+    let mut total = 0;
+    for line in data.lines() {
+        let (compartment_a, compartment_b) = line.split_at(line.len() / 2);
+        let mut a_characters: Vec<char> = compartment_a.chars().collect();
+        a_characters.sort();
+        a_characters.dedup();
+        for character in a_characters {
+            if compartment_b.contains(character) {
+                total += get_item_weight(character);
+            }
+        }
+    }
+    total
+}
+
+///
+/// The original part 2, concatenating compartments and searching for each item in turn.
+///
+fn naive_badges(data: &str) -> u64 {
+    let lines: Vec<&str> = data.lines().collect();
+    let mut total = 0;
+    for group in lines.chunks(3) {
+        let badge = group[0].chars()
+            .find(|x| group[1].contains(*x) && group[2].contains(*x))
+            .expect("Could not find badge item");
+        total += get_item_weight(badge);
+    }
+    total
+}
+
+///
+/// Time one of the part 1 or part 2 totals, printing it next to the time taken
+///
+fn time<F: FnOnce() -> u64>(label: &str, f: F) -> u64 {
+    let start = Instant::now();
+    let result = f();
+    println!("{:<24} {:>12} {:>10.2?}", label, result, start.elapsed());
+    result
+}
+
+///
+/// Run the benchmark
+///
+pub fn run() {
+    let mut rng = Rng { state: 0x2022_0003 };
+    let data = generate_data(&mut rng, RUCKSACK_COUNT);
+    println!("{} rucksacks, {} bytes", RUCKSACK_COUNT, data.len());
+
+    let naive_1 = time("naive priorities", || naive_priorities(&data));
+    let naive_2 = time("naive badges", || naive_badges(&data));
+
+    let start = Instant::now();
+    let mut alphabet = ItemAlphabet::letters();
+    let rucksacks = generate_rucksacks(&data, 2, &mut alphabet).unwrap();
+    println!("{:<24} {:>12} {:>10.2?}", "bitset parse", rucksacks.len(), start.elapsed());
+    let bitset_1 = time("bitset priorities", || rucksacks.iter().map(|x| x.get_rucksack_priority(&alphabet)).sum());
+    let bitset_2 = time("bitset badges", || check_rucksack_groups(&rucksacks, 3, &alphabet).unwrap());
+
+    assert_eq!(naive_1, bitset_1);
+    assert_eq!(naive_2, bitset_2);
+}
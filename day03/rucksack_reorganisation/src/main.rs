@@ -1,129 +1,398 @@
-/// 
+///
 /// AOC 2022 Day 3
 ///
-/// Not the shortest solution here. This can probably be refactored.
+/// Items are stored as bitsets keyed on their index in an item alphabet, so finding the common
+/// items between compartments or rucksacks is just a bitwise AND.
 ///
-use std::fs;
+use std::{env, fmt, fs};
+
+mod alphabet;
+mod bench;
+mod repair;
 
-/// 
-/// Rucksack struct representing the two compartments in a rucksack
+use alphabet::ItemAlphabet;
+
+///
+/// ItemSet, a set of item types stored as a bitmask.
+///
+/// Bit n is set when the item with alphabet index n is present. The first 64 items live inline,
+/// so the puzzle's 52 letters never need an allocation, and larger alphabets spill into extra
+/// words.
+///
+#[derive(Debug, Clone, Default)]
+struct ItemSet {
+    low: u64,
+    high: Vec<u64>,
+}
+
+impl ItemSet {
+    ///
+    /// Add the item with a given alphabet index to the set
+    ///
+    fn insert(&mut self, index: usize) {
+        match index / 64 {
+            0 => self.low |= 1 << index,
+            word => {
+                if self.high.len() < word {
+                    self.high.resize(word, 0);
+                }
+                self.high[word - 1] |= 1 << (index % 64);
+            }
+        }
+    }
+    ///
+    /// Check whether the item with a given alphabet index is in the set
+    ///
+    fn contains(&self, index: usize) -> bool {
+        match index / 64 {
+            0 => self.low & (1 << index) != 0,
+            word => self.high.get(word - 1).is_some_and(|x| x & (1 << (index % 64)) != 0),
+        }
+    }
+    ///
+    /// Items that appear in both sets
+    ///
+    fn intersection(&self, oth: &ItemSet) -> ItemSet {
+        ItemSet {
+            low: self.low & oth.low,
+            high: self.high.iter().zip(oth.high.iter()).map(|(x, y)| x & y).collect(),
+        }
+    }
+    ///
+    /// Items that appear in either set
+    ///
+    fn union(&self, oth: &ItemSet) -> ItemSet {
+        let (long, short) = match self.high.len() >= oth.high.len() {
+            true => (self, oth),
+            false => (oth, self),
+        };
+        let mut high = long.high.clone();
+        for (x, y) in high.iter_mut().zip(short.high.iter()) {
+            *x |= y;
+        }
+        ItemSet { low: self.low | oth.low, high }
+    }
+    ///
+    /// Iterate over the alphabet indices of the items in the set, in ascending order
+    ///
+    fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        std::iter::once(self.low)
+            .chain(self.high.iter().copied())
+            .enumerate()
+            .flat_map(|(word, mut bits)| {
+                std::iter::from_fn(move || {
+                    if bits == 0 {
+                        return None;
+                    }
+                    let bit = bits.trailing_zeros() as usize;
+                    bits &= bits - 1;
+                    Some(word * 64 + bit)
+                })
+            })
+    }
+    ///
+    /// Iterate over the items in the set
+    ///
+    fn items<'a>(&'a self, alphabet: &'a ItemAlphabet) -> impl Iterator<Item = char> + 'a {
+        self.indices().map(|x| alphabet.item(x))
+    }
+    ///
+    /// Sum of the priorities of every item in the set
+    ///
+    fn priority(&self, alphabet: &ItemAlphabet) -> u64 {
+        self.indices().map(|x| alphabet.priority(x)).sum()
+    }
+}
+
+///
+/// Rucksack struct representing the equally sized compartments in a rucksack
 ///
 struct Rucksack {
-    compartment_a: String,
-    compartment_b: String,
+    compartments: Vec<ItemSet>,
 }
 
 impl Rucksack {
-    /// 
-    /// Between the two compartments of a rucksack, get the items that appear in both, and
+    ///
+    /// Between the compartments of a rucksack, get the items that appear in more than one, and
     /// calculate their overall priority.
     ///
-    fn get_rucksack_priority(&self) -> u64 {
-        self.get_priority_items().iter().map(|x| self.get_item_weight(&x)).sum::<u64>()
+    fn get_rucksack_priority(&self, alphabet: &ItemAlphabet) -> u64 {
+        self.get_priority_items().priority(alphabet)
     }
 
-    /// 
-    /// Get the weight of an character.
     ///
-    /// Uses the ascii value of a character using modulo to set the values appropriately.
+    /// Get the priority items in a rucksack, those that appear in at least two compartments
     ///
-    fn get_item_weight(&self, item: &char) -> u64 {
-        match item.is_ascii_lowercase() {
-            true => *item as u64 % 96,
-            false => (*item as u64 % 64) + 26,
+    fn get_priority_items(&self) -> ItemSet {
+        let mut seen = ItemSet::default();
+        let mut repeated = ItemSet::default();
+        for compartment in self.compartments.iter() {
+            repeated = repeated.union(&seen.intersection(compartment));
+            seen = seen.union(compartment);
         }
+        repeated
     }
 
-    /// 
-    /// Get the priority items in a rucksack
     ///
-    fn get_priority_items(&self) -> Vec<char> {
-        let a_characters: Vec<char> = self.get_unique_items();
-        let mut matching: Vec<char> = vec![];
-        for character in a_characters {
-            if self.compartment_b.contains(character) {
-                matching.push(character)
-            }
+    /// Get every item in the rucksack, regardless of compartment
+    ///
+    fn get_items(&self) -> ItemSet {
+        self.compartments.iter().fold(ItemSet::default(), |x, y| x.union(y))
+    }
+}
+
+///
+/// Errors that can occur when reading rucksacks or searching groups for badges.
+///
+/// Lines and groups are numbered from 1, to match the puzzle input.
+///
+#[derive(Debug, PartialEq, Eq)]
+enum RucksackError {
+    UnevenCompartments { line: usize, items: usize, compartments: usize },
+    UnknownItem { line: usize, column: usize, item: char },
+    IncompleteGroup { rucksacks: usize, group_size: usize },
+    NoBadge { group: usize },
+    AmbiguousBadge { group: usize, candidates: Vec<char> },
+}
+
+impl fmt::Display for RucksackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RucksackError::UnevenCompartments { line, items, compartments } => write!(
+                f, "line {}: {} items cannot be split into {} equal compartments", line, items, compartments
+            ),
+            RucksackError::UnknownItem { line, column, item } => write!(
+                f, "line {}, column {}: {:?} is not in the item alphabet", line, column, item
+            ),
+            RucksackError::IncompleteGroup { rucksacks, group_size } => write!(
+                f, "{} rucksacks cannot be split into groups of {}", rucksacks, group_size
+            ),
+            RucksackError::NoBadge { group } => write!(f, "group {}: no badge item", group),
+            RucksackError::AmbiguousBadge { group, candidates } => write!(
+                f, "group {}: more than one badge candidate {:?}", group, candidates
+            ),
         }
-        matching
     }
+}
 
-    /// 
-    /// Get a vector of unique items in compartment a
-    ///
-    fn get_unique_items(&self) -> Vec<char> {
-        let mut a_characters: Vec<char> = self.compartment_a.chars().collect();
-        a_characters.sort();
-        a_characters.dedup();
-        a_characters
+///
+/// Get the weight of an character.
+///
+/// Uses the ascii value of a character using modulo to set the values appropriately. Only valid
+/// for a-z and A-Z, this is the priority scheme of the default alphabet.
+///
+fn get_item_weight(item: char) -> u64 {
+    match item.is_ascii_lowercase() {
+        true => item as u64 % 96,
+        false => (item as u64 % 64) + 26,
     }
-    
-    /// 
-    /// Check the rucksack to see if the item exists in either compartment
-    ///
-    fn find_item(&self, item: &char) -> bool {
-        match (self.compartment_a.clone() + &self.compartment_b).contains(*item) {
-            true => true,
-            false => false,
-        }
+}
+
+///
+/// Read a single line of items, calling back with the compartment and alphabet index of each item
+///
+fn scan_line<F: FnMut(usize, usize)>(
+    line: &str,
+    line_number: usize,
+    compartments: usize,
+    alphabet: &mut ItemAlphabet,
+    mut f: F,
+) -> Result<(), RucksackError> {
+    let items = line.chars().count();
+    if compartments == 0 || !items.is_multiple_of(compartments) {
+        return Err(RucksackError::UnevenCompartments { line: line_number, items, compartments });
+    }
+    for (column, item) in line.chars().enumerate() {
+        let index = alphabet.index(item)
+            .map_err(|_| RucksackError::UnknownItem { line: line_number, column: column + 1, item })?;
+        f(column / (items / compartments), index);
     }
+    Ok(())
 }
 
-/// 
-/// Generate the rucksack data from each line in the data
 ///
-fn generate_rucksacks(data: &str) -> Vec<Rucksack> {
+/// Generate the rucksack data from each line in the data, splitting each line into a number of
+/// equally sized compartments
+///
+fn generate_rucksacks(
+    data: &str,
+    compartments: usize,
+    alphabet: &mut ItemAlphabet,
+) -> Result<Vec<Rucksack>, RucksackError> {
     let mut rucksacks: Vec<Rucksack> = vec![];
-    for line in data.lines() {
-        if line.len() % 2 != 0 {
-            panic!("Incorrect amount of items in rucksack!");
-        }
-        let split_data = line.split_at(line.len() / 2);
-        rucksacks.push( 
-            Rucksack { 
-                compartment_a: String::from(split_data.0),
-                compartment_b: String::from(split_data.1)
-            }
-        )
+    for (i, line) in data.lines().enumerate() {
+        let mut sets = vec![ItemSet::default(); compartments];
+        scan_line(line, i + 1, compartments, alphabet, |compartment, index| sets[compartment].insert(index))?;
+        rucksacks.push(Rucksack { compartments: sets })
     }
-    rucksacks
+    Ok(rucksacks)
 }
 
-/// 
+///
 /// Check the rucksack groups for their badge items, and calculate the priority
 ///
-fn check_rucksack_groups(rucksacks: &Vec<Rucksack>, group_size: usize) -> u64 {
-    if rucksacks.len() % group_size != 0 {
-        panic!("Insufficient group sizes")
+fn check_rucksack_groups(
+    rucksacks: &[Rucksack],
+    group_size: usize,
+    alphabet: &ItemAlphabet,
+) -> Result<u64, RucksackError> {
+    if group_size == 0 || !rucksacks.len().is_multiple_of(group_size) {
+        return Err(RucksackError::IncompleteGroup { rucksacks: rucksacks.len(), group_size });
     }
-    let mut group_split = rucksacks.split_at(group_size);
-    let mut weight_total = 0;
-    while group_split.1.len() > 0 {
-        weight_total += find_badge(group_split.0);
-        group_split = group_split.1.split_at(group_size);
+    rucksacks
+        .chunks(group_size)
+        .enumerate()
+        .map(|(i, x)| find_badge(x, i + 1, alphabet).map(|x| alphabet.priority(x)))
+        .sum()
+}
+
+///
+/// Find the badge item in a rucksack group of any size, returning its alphabet index.
+///
+/// The badge is the only item carried by every rucksack in the group.
+///
+fn find_badge(rucksacks: &[Rucksack], group: usize, alphabet: &ItemAlphabet) -> Result<usize, RucksackError> {
+    let common = rucksacks.iter()
+        .map(|x| x.get_items())
+        .reduce(|x, y| x.intersection(&y))
+        .unwrap_or_default();
+    let mut candidates = common.indices();
+    match (candidates.next(), candidates.next()) {
+        (Some(index), None) => Ok(index),
+        (None, _) => Err(RucksackError::NoBadge { group }),
+        (Some(_), Some(_)) => Err(
+            RucksackError::AmbiguousBadge { group, candidates: common.items(alphabet).collect() }
+        ),
     }
-    // Add the last iteration as it is missed by the while loop
-    weight_total += find_badge(group_split.0);
-    weight_total
 }
 
-/// 
-/// Find the badge item in rucksack groups 
 ///
-fn find_badge(rucksacks: &[Rucksack]) -> u64 {
-    for item in (rucksacks[0].compartment_a.clone() + &rucksacks[0].compartment_b).chars() {
-        if rucksacks[1].find_item(&item) && rucksacks[2].find_item(&item) {
-            return rucksacks[0].get_item_weight(&item);
-        };
+/// Command line options
+///
+struct Options {
+    alphabet: ItemAlphabet,
+    input: String,
+    repair: bool,
+    compartments: usize,
+    group_size: usize,
+}
+
+///
+/// Read the options from the command line
+///
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut alphabet = ItemAlphabet::letters();
+    let mut input = String::from("data.txt");
+    let mut repair = false;
+    let (mut compartments, mut group_size) = (2, 3);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--alphabet" => alphabet = match value()?.as_str() {
+                "letters" => ItemAlphabet::letters(),
+                "digits" => ItemAlphabet::digits(),
+                "unicode" => ItemAlphabet::unicode(),
+                oth => return Err(format!("unknown alphabet {:?}, expected letters, digits or unicode", oth)),
+            },
+            "--priorities" => {
+                let path = value()?;
+                let table = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+                alphabet = ItemAlphabet::from_priority_table(&table).map_err(|e| format!("{}: {}", path, e))?;
+            }
+            "--input" => input = value()?.clone(),
+            "--repair" => repair = true,
+            "--compartments" => compartments = parse_count(arg, value()?)?,
+            "--group-size" => group_size = parse_count(arg, value()?)?,
+            oth => return Err(format!("unknown argument {:?}", oth)),
+        }
     }
-    panic!("Could not find badge item");
+    if repair && compartments != 2 {
+        return Err(String::from("--repair only works with 2 compartments"));
+    }
+    Ok(Options { alphabet, input, repair, compartments, group_size })
 }
 
-/// 
+///
+/// Parse the value of an option that counts something, which has to be at least 1
+///
+fn parse_count(option: &str, value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(count) if count > 0 => Ok(count),
+        _ => Err(format!("{} needs a number above 0, not {:?}", option, value)),
+    }
+}
+
+///
+/// Print the repair plan for every rucksack and group, reporting a group error after the repairs
+///
+fn print_repairs(data: &str, group_size: usize, alphabet: &mut ItemAlphabet) -> Result<(), RucksackError> {
+    let (repairs, groups) = repair::plan(data, group_size, alphabet)?;
+    for repair in repairs {
+        match repair.swaps {
+            None => println!("Rucksack {}: impossible to repair", repair.line),
+            Some(swaps) if swaps.is_empty() => println!("Rucksack {}: already sorted", repair.line),
+            Some(swaps) => println!(
+                "Rucksack {}: {} swap(s) {}",
+                repair.line,
+                swaps.len(),
+                swaps.iter().map(|(x, y)| format!("{}<->{}", x, y)).collect::<Vec<String>>().join(" "),
+            ),
+        }
+    }
+    for group in groups? {
+        match (group.badge, group.badge_less.is_empty()) {
+            (Err(e), _) => println!("Error: {}", e),
+            (Ok(badge), true) => println!("Group {}: badge {}", group.group, badge),
+            (Ok(badge), false) => println!(
+                "Group {}: badge {}, lost by rucksack(s) {:?} after repair", group.group, badge, group.badge_less
+            ),
+        }
+    }
+    Ok(())
+}
+
+///
 /// Entrypoint
 ///
+/// Pass `bench` as the first argument to time the bitset approach against the original string
+/// based one on generated data. Otherwise the options are:
+///
+/// * `--alphabet letters|digits|unicode` - the item alphabet, letters by default
+/// * `--priorities <file>` - a custom alphabet, one `<item> <priority>` per line
+/// * `--input <file>` - the puzzle input, data.txt by default
+/// * `--compartments K` - split each rucksack into K equal compartments, 2 by default
+/// * `--group-size N` - look for a badge in every N rucksacks, 3 by default
+/// * `--repair` - print the swaps needed to repair each rucksack instead of the answers, which
+///   needs 2 compartments
+///
 fn main() {
-    let rucksacks = generate_rucksacks(&fs::read_to_string("data.txt").unwrap());
-    println!("Part 1: {}", rucksacks.iter().map(|x| x.get_rucksack_priority()).sum::<u64>());
-    println!("Part 2: {}", check_rucksack_groups(&rucksacks, 3));
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("bench") {
+        bench::run();
+        return;
+    }
+    let Options { mut alphabet, input, repair, compartments, group_size } = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => return eprintln!("Error: {}", e),
+    };
+    let data = match fs::read_to_string(&input) {
+        Ok(data) => data,
+        Err(e) => return eprintln!("Error: {}: {}", input, e),
+    };
+    if repair {
+        if let Err(e) = print_repairs(&data, group_size, &mut alphabet) {
+            eprintln!("Error: {}", e);
+        }
+        return;
+    }
+    let rucksacks = match generate_rucksacks(&data, compartments, &mut alphabet) {
+        Ok(rucksacks) => rucksacks,
+        Err(e) => return eprintln!("Error: {}", e),
+    };
+    println!("Part 1: {}", rucksacks.iter().map(|x| x.get_rucksack_priority(&alphabet)).sum::<u64>());
+    match check_rucksack_groups(&rucksacks, group_size, &alphabet) {
+        Ok(total) => println!("Part 2: {}", total),
+        Err(e) => eprintln!("Error: {}", e),
+    }
 }
@@ -0,0 +1,161 @@
+///
+/// Rucksack repair planner.
+///
+/// Every item type should live in exactly one of the two compartments. A swap exchanges one item
+/// in the first compartment with one item in the second, so compartment sizes never change. For
+/// each rucksack the planner picks which compartment each item type belongs to, minimising the
+/// number of swaps needed, or reports that no split of the item types fills both halves exactly.
+///
+use std::collections::BTreeMap;
+
+use super::{find_badge, scan_line, ItemAlphabet, ItemSet, Rucksack, RucksackError};
+
+///
+/// The repair plan for a single rucksack
+///
+pub struct RucksackRepair {
+    pub line: usize,
+    pub swaps: Option<Vec<(char, char)>>,
+    repaired: Option<Rucksack>,
+}
+
+///
+/// The badge of a group, and the rucksacks that would no longer carry it once repaired.
+///
+/// Swaps only move items between compartments, so a valid plan never loses a badge. The check is
+/// made against the repaired packing so that a faulty plan shows up here.
+///
+pub struct GroupRepair {
+    pub group: usize,
+    pub badge: Result<char, RucksackError>,
+    pub badge_less: Vec<usize>,
+}
+
+///
+/// The badge check for every group, or why the rucksacks cannot be split into groups
+///
+pub type GroupRepairs = Result<Vec<GroupRepair>, RucksackError>;
+
+///
+/// Item counts for each type in a two compartment rucksack, keyed on alphabet index
+///
+type ItemCounts = BTreeMap<usize, (usize, usize)>;
+
+///
+/// The cheapest assignment of item types to compartments, and the swaps that achieve it
+///
+struct SwapPlan {
+    swaps: Vec<(usize, usize)>,
+    first: ItemSet,
+    second: ItemSet,
+}
+
+///
+/// Find the cheapest way of assigning every item type to a compartment.
+///
+/// This is a subset sum over item types: the types sent to the first compartment must total
+/// exactly half of the items. Every item that has to change compartment costs half a swap, as each
+/// swap moves one item each way. Returns the swaps as pairs of (item leaving the first
+/// compartment, item leaving the second), or None when no assignment exists.
+///
+fn plan_swaps(counts: &ItemCounts, half: usize) -> Option<SwapPlan> {
+    // layers[t][s] is the cheapest cost of filling s slots of the first compartment using the
+    // first t item types
+    let mut layers: Vec<Vec<Option<usize>>> = vec![vec![None; half + 1]];
+    layers[0][0] = Some(0);
+    for (a, b) in counts.values() {
+        let previous = layers.last().unwrap();
+        let mut next = previous.clone();
+        for s in 0..=half {
+            next[s] = next[s].map(|x| x + a);
+            if s >= a + b {
+                if let Some(cost) = previous[s - a - b] {
+                    next[s] = Some(next[s].map_or(cost + b, |x| x.min(cost + b)));
+                }
+            }
+        }
+        layers.push(next);
+    }
+    layers.last().unwrap()[half]?;
+
+    // Walk back through the layers to recover the assignment
+    let mut first = ItemSet::default();
+    let mut second = ItemSet::default();
+    let mut to_first: Vec<usize> = vec![];
+    let mut to_second: Vec<usize> = vec![];
+    let mut s = half;
+    for (t, (index, (a, b))) in counts.iter().enumerate().rev() {
+        let cost = layers[t + 1][s].unwrap();
+        if s >= a + b && layers[t][s - a - b].is_some_and(|x| x + b == cost) {
+            first.insert(*index);
+            to_first.extend(std::iter::repeat_n(*index, *b));
+            s -= a + b;
+        } else {
+            second.insert(*index);
+            to_second.extend(std::iter::repeat_n(*index, *a));
+        }
+    }
+    Some(SwapPlan { swaps: to_second.into_iter().zip(to_first).collect(), first, second })
+}
+
+///
+/// Plan the repair of every rucksack, then check each group's badge against the repaired packing.
+///
+/// Rucksacks that cannot be repaired are left as they are when checking the badges. The repairs
+/// do not depend on the groups, so they are still returned when the rucksacks cannot be split into
+/// groups, with the group error in place of the badges.
+///
+pub fn plan(
+    data: &str,
+    group_size: usize,
+    alphabet: &mut ItemAlphabet,
+) -> Result<(Vec<RucksackRepair>, GroupRepairs), RucksackError> {
+    let mut repairs: Vec<RucksackRepair> = vec![];
+    let mut rucksacks: Vec<Rucksack> = vec![];
+    for (i, line) in data.lines().enumerate() {
+        let mut counts = ItemCounts::new();
+        let mut compartments = vec![ItemSet::default(); 2];
+        scan_line(line, i + 1, 2, alphabet, |compartment, index| {
+            let count = counts.entry(index).or_insert((0, 0));
+            match compartment {
+                0 => count.0 += 1,
+                _ => count.1 += 1,
+            }
+            compartments[compartment].insert(index);
+        })?;
+        let plan = plan_swaps(&counts, line.chars().count() / 2);
+        repairs.push(RucksackRepair {
+            line: i + 1,
+            swaps: plan.as_ref().map(|x| {
+                x.swaps.iter().map(|(a, b)| (alphabet.item(*a), alphabet.item(*b))).collect()
+            }),
+            repaired: plan.map(|x| Rucksack { compartments: vec![x.first, x.second] }),
+        });
+        rucksacks.push(Rucksack { compartments });
+    }
+
+    if group_size == 0 || !rucksacks.len().is_multiple_of(group_size) {
+        let error = RucksackError::IncompleteGroup { rucksacks: rucksacks.len(), group_size };
+        return Ok((repairs, Err(error)));
+    }
+    let groups = rucksacks
+        .chunks(group_size)
+        .zip(repairs.chunks(group_size))
+        .enumerate()
+        .map(|(i, (group, group_repairs))| {
+            let badge = find_badge(group, i + 1, alphabet);
+            let badge_less = match &badge {
+                Ok(badge) => group.iter()
+                    .zip(group_repairs)
+                    .filter(|(rucksack, repair)| {
+                        !repair.repaired.as_ref().unwrap_or(rucksack).get_items().contains(*badge)
+                    })
+                    .map(|(_, repair)| repair.line)
+                    .collect(),
+                Err(_) => vec![],
+            };
+            GroupRepair { group: i + 1, badge: badge.map(|x| alphabet.item(x)), badge_less }
+        })
+        .collect();
+    Ok((repairs, Ok(groups)))
+}
@@ -0,0 +1,207 @@
+///
+/// Camp wide coverage analysis.
+///
+/// Every assignment is turned into a start and end event and swept in order, splitting the camp
+/// into segments where the number of elves on each section is constant. All of the queries are
+/// then answered from the segments, giving O(n log n) overall.
+///
+use std::fmt;
+
+use crate::sections::{Bound, IntervalSet, Sections};
+
+///
+/// A single elf's assignment, along with where it came from in the input
+///
+#[derive(Debug, Clone)]
+pub struct Assignment<T: Bound = u64> {
+    pub line: usize,
+    pub elf: usize,
+    pub label: Option<String>,
+    pub sections: Sections<T>,
+}
+
+impl<T: Bound> fmt::Display for Assignment<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.label {
+            Some(label) => write!(f, "line {} {} ({})", self.line, label, self.sections),
+            None => write!(f, "line {} elf {} ({})", self.line, self.elf, self.sections),
+        }
+    }
+}
+
+///
+/// An elf whose whole assignment is also covered by other elves.
+///
+/// Spare cover is the smallest number of other elves on any one of its sections.
+///
+#[derive(Debug, Clone)]
+pub struct Redundant<T: Bound = u64> {
+    pub assignment: Assignment<T>,
+    pub spare_cover: usize,
+}
+
+///
+/// The result of sweeping over the whole camp
+///
+#[derive(Debug)]
+pub struct CampReport<T: Bound = u64> {
+    pub threshold: usize,
+    pub uncovered: IntervalSet<T>,
+    pub over_threshold: IntervalSet<T>,
+    pub max_elves: usize,
+    pub busiest: IntervalSet<T>,
+    pub redundant: Vec<Redundant<T>>,
+}
+
+///
+/// Split the camp into consecutive segments, each with the number of elves covering it
+///
+fn sweep<T: Bound>(assignments: &[Assignment<T>]) -> Vec<(Sections<T>, usize)> {
+    // Ends are stored as the section after the range, or None past the largest possible bound
+    let mut starts: Vec<T> = assignments.iter().map(|x| x.sections.start).collect();
+    let mut ends: Vec<Option<T>> = assignments.iter().map(|x| x.sections.end.next()).collect();
+    starts.sort();
+    ends.sort_by(|a, b| match (a, b) {
+        (None, None) => std::cmp::Ordering::Equal,
+        (None, _) => std::cmp::Ordering::Greater,
+        (_, None) => std::cmp::Ordering::Less,
+        (Some(a), Some(b)) => a.cmp(b),
+    });
+
+    let mut segments: Vec<(Sections<T>, usize)> = vec![];
+    let (mut i, mut j, mut depth) = (0, 0, 0);
+    let mut from: Option<T> = None;
+    while j < ends.len() {
+        let position = match (starts.get(i), ends[j]) {
+            (Some(start), Some(end)) => Some(*start.min(&end)),
+            (Some(start), None) => Some(*start),
+            (None, end) => end,
+        };
+        if let (Some(from), Some(position)) = (from, position) {
+            if from < position {
+                segments.push((Sections { start: from, end: position.prev().unwrap() }, depth));
+            }
+        }
+        let Some(position) = position else {
+            // Everything left runs to the largest possible bound
+            segments.push((Sections { start: from.unwrap(), end: assignments_max(assignments) }, depth));
+            break;
+        };
+        while i < starts.len() && starts[i] == position {
+            depth += 1;
+            i += 1;
+        }
+        while j < ends.len() && ends[j] == Some(position) {
+            depth -= 1;
+            j += 1;
+        }
+        from = Some(position);
+    }
+    segments
+}
+
+///
+/// The largest section of any assignment
+///
+fn assignments_max<T: Bound>(assignments: &[Assignment<T>]) -> T {
+    assignments.iter().map(|x| x.sections.end).max().unwrap()
+}
+
+///
+/// Sparse table answering range minimum queries over the segment depths in O(1)
+///
+struct MinTable {
+    levels: Vec<Vec<usize>>,
+}
+
+impl MinTable {
+    ///
+    /// Build the table from a list of values
+    ///
+    fn new(values: Vec<usize>) -> MinTable {
+        let mut levels = vec![values];
+        let mut width = 1;
+        while width * 2 <= levels[0].len() {
+            let previous = levels.last().unwrap();
+            let next = (0..=levels[0].len() - width * 2)
+                .map(|i| previous[i].min(previous[i + width]))
+                .collect();
+            levels.push(next);
+            width *= 2;
+        }
+        MinTable { levels }
+    }
+    ///
+    /// The smallest value between two indices, inclusive
+    ///
+    fn min(&self, from: usize, to: usize) -> usize {
+        let level = (to - from + 1).ilog2() as usize;
+        self.levels[level][from].min(self.levels[level][to + 1 - (1 << level)])
+    }
+}
+
+///
+/// Sweep over every assignment in the camp and build the report
+///
+pub fn analyse<T: Bound>(assignments: &[Assignment<T>], threshold: usize) -> CampReport<T> {
+    if assignments.is_empty() {
+        return CampReport {
+            threshold,
+            uncovered: IntervalSet::default(),
+            over_threshold: IntervalSet::default(),
+            max_elves: 0,
+            busiest: IntervalSet::default(),
+            redundant: vec![],
+        };
+    }
+    let segments = sweep(assignments);
+    let max_elves = segments.iter().map(|x| x.1).max().unwrap_or(0);
+    let matching = |f: &dyn Fn(usize) -> bool| -> IntervalSet<T> {
+        segments.iter().filter(|x| f(x.1)).map(|x| x.0).collect()
+    };
+
+    // An elf is redundant when every one of its sections has at least one other elf on it
+    let table = MinTable::new(segments.iter().map(|x| x.1).collect());
+    let segment_of = |section: T| segments.partition_point(|x| x.0.end < section);
+    let mut redundant: Vec<Redundant<T>> = assignments.iter()
+        .map(|x| Redundant {
+            assignment: x.clone(),
+            spare_cover: table.min(segment_of(x.sections.start), segment_of(x.sections.end)) - 1,
+        })
+        .filter(|x| x.spare_cover > 0)
+        .collect();
+    redundant.sort_by(|a, b| {
+        b.assignment.sections.size().cmp(&a.assignment.sections.size())
+            .then(b.spare_cover.cmp(&a.spare_cover))
+            .then(a.assignment.line.cmp(&b.assignment.line))
+    });
+
+    CampReport {
+        threshold,
+        uncovered: matching(&|x| x == 0),
+        over_threshold: matching(&|x| x > threshold),
+        max_elves,
+        busiest: matching(&|x| x == max_elves),
+        redundant,
+    }
+}
+
+impl<T: Bound> fmt::Display for CampReport<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |x: &IntervalSet<T>| match x.is_empty() {
+            true => String::from("none"),
+            false => x.ranges().iter().map(|x| x.to_string()).collect::<Vec<String>>().join(", "),
+        };
+        writeln!(f, "Uncovered sections: {}", list(&self.uncovered))?;
+        writeln!(f, "Covered by more than {} elves: {}", self.threshold, list(&self.over_threshold))?;
+        writeln!(f, "Most elves on one section: {} ({})", self.max_elves, list(&self.busiest))?;
+        writeln!(f, "Redundant elves: {}", self.redundant.len())?;
+        for (rank, elf) in self.redundant.iter().enumerate() {
+            writeln!(
+                f, "{:>5}. {}, {} sections, covered by at least {} other(s)",
+                rank + 1, elf.assignment, elf.assignment.sections.size(), elf.spare_cover
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+///
+/// Assignment groups, a line of any number of comma separated assignments.
+///
+/// Each assignment may be prefixed with a label, for example `alice=2-4,bob=6-8,carol=3-7`.
+///
+use std::fmt;
+
+use crate::sections::{Bound, Sections, SectionsError};
+
+///
+/// A single elf in a group
+///
+#[derive(Debug, Clone)]
+pub struct Member<T: Bound = u64> {
+    pub label: Option<String>,
+    pub sections: Sections<T>,
+}
+
+impl<T: Bound> Member<T> {
+    ///
+    /// Parse an `[label=]start-end` entry
+    ///
+    fn new(data: &str) -> Result<Member<T>, SectionsError> {
+        let (label, range) = match data.split_once('=') {
+            Some((label, _)) if label.trim().is_empty() => {
+                return Err(SectionsError::EmptyLabel { entry: data.to_string() })
+            }
+            Some((label, range)) => (Some(label.trim().to_string()), range),
+            None => (None, data),
+        };
+        Ok(Member { label, sections: Sections::new(range.trim())? })
+    }
+}
+
+impl<T: Bound> fmt::Display for Member<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.label {
+            Some(label) => write!(f, "{}={}", label, self.sections),
+            None => write!(f, "{}", self.sections),
+        }
+    }
+}
+
+///
+/// Group struct housing every assignment from a line of the input
+///
+#[derive(Debug, Clone)]
+pub struct Group<T: Bound = u64> {
+    pub members: Vec<Member<T>>,
+}
+
+impl<T: Bound> Group<T> {
+    ///
+    /// Parse a line of data into a group of assignments
+    ///
+    pub fn new(data: &str) -> Result<Group<T>, SectionsError> {
+        if data.trim().is_empty() {
+            return Err(SectionsError::Empty);
+        }
+        Ok(Group { members: data.split(',').map(Member::new).collect::<Result<Vec<Member<T>>, _>>()? })
+    }
+    ///
+    /// Find a member whose assignment fully contains every other member's
+    ///
+    pub fn container(&self) -> Option<&Member<T>> {
+        self.members
+            .iter()
+            .find(|x| self.members.iter().all(|y| x.sections.fully_contains(&y.sections)))
+    }
+    ///
+    /// Count the pairs of members within the group whose assignments overlap
+    ///
+    pub fn overlapping_pairs(&self) -> usize {
+        self.members
+            .iter()
+            .enumerate()
+            .map(|(i, x)| self.members[i + 1..].iter().filter(|y| x.sections.overlaps(&y.sections)).count())
+            .sum()
+    }
+}
+
+impl<T: Bound> fmt::Display for Group<T> {
+    ///
+    /// Write the group back out in the input format
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let members: Vec<String> = self.members.iter().map(|x| x.to_string()).collect();
+        write!(f, "{}", members.join(","))
+    }
+}
@@ -1,61 +1,131 @@
 /// 
 /// AOC 2022 Day 4
 ///
-use std::fs;
+/// Each line is a group of elves, and each elf's assignment is a Sections range. The containment
+/// and overlap checks are made with the IntervalSet set algebra in the sections module.
+///
+/// Use `--input <file>` to read something other than data.txt, and pass `--report` to sweep over
+/// the whole camp instead, optionally with `--threshold N` to list the sections covered by more
+/// than N elves (1 by default). Pass `--reassign group|camp` to print a new assignment list with
+/// the overlaps removed within each group or across the camp.
+///
+use std::{env, fs};
+
+mod coverage;
+mod group;
+mod reassign;
+mod sections;
+
+use coverage::Assignment;
+use group::Group;
+use reassign::Scope;
+use sections::IntervalSet;
 /// 
-/// Section struct housing the beginning and end sections for a given elf
+/// Parse every line of data into a group, naming the line of the first bad entry
 ///
-struct Sections {
-    start: u64,
-    end: u64,
+fn parse_groups(data: &str) -> Result<Vec<Group>, String> {
+    data.lines()
+        .enumerate()
+        .map(|(i, line)| Group::new(line).map_err(|e| format!("line {}: {}", i + 1, e)))
+        .collect()
 }
-
-impl Sections {
-    /// 
-    /// Create a new Sections instance
-    ///
-    fn new(data: &str) -> Sections {
-        let mut split_data = data.split('-');
-        let start = split_data.next().unwrap().parse::<u64>().unwrap();
-        let end = split_data.next().unwrap().parse::<u64>().unwrap();
-        Sections { start, end }
-    }
-    /// 
-    /// Check that this section fully contains another
-    ///
-    fn fully_contains(&self, oth: &Sections) -> bool {
-        self.start <= oth.start && self.end >= oth.end
-    }
-    /// 
-    /// Check that this section overlaps with another
-    ///
-    fn overlaps(&self, oth: &Sections) -> bool {
-        (self.start >= oth.start && self.start <= oth.end) || 
-            (oth.start >= self.start && oth.start <= self.end)
-    }
+/// 
+/// Print the camp wide coverage report
+///
+fn report(groups: &[Group], threshold: usize) {
+    let assignments: Vec<Assignment> = groups.iter()
+        .enumerate()
+        .flat_map(|(i, group)| {
+            group.members.iter().enumerate().map(move |(j, member)| Assignment {
+                line: i + 1,
+                elf: j + 1,
+                label: member.label.clone(),
+                sections: member.sections,
+            })
+        })
+        .collect();
+    print!("{}", coverage::analyse(&assignments, threshold));
 }
 /// 
-/// Parse a line of data into a tuple of two Sections
+/// Print the re-assigned groups in the input format, with a summary of the change on stderr.
 ///
-fn parse_pair(data: &str) -> (Sections, Sections) {
-    let mut split_data = data.split(',');
-    (Sections::new(split_data.next().unwrap()), Sections::new(split_data.next().unwrap()))
+/// Groups that could not be re-assigned are printed unchanged so the output can still be fed back
+/// in as the input.
+///
+fn print_reassigned(groups: &[Group], scope: Scope) {
+    let (reassigned, errors) = reassign::reassign(groups, scope);
+    for group in reassigned.iter() {
+        println!("{}", group);
+    }
+    for e in errors.iter() {
+        eprintln!("Left unchanged, {}", e);
+    }
+    eprintln!(
+        "Largest workload: {} sections, was {}",
+        reassign::max_workload(&reassigned),
+        reassign::max_workload(groups)
+    );
 }
 /// 
 /// Entrypoint
 ///
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let input = match args.iter().position(|x| x == "--input") {
+        Some(i) => match args.get(i + 1) {
+            Some(input) => input.as_str(),
+            None => return eprintln!("Error: --input needs a file"),
+        },
+        None => "data.txt",
+    };
+    let data = match fs::read_to_string(input) {
+        Ok(data) => data,
+        Err(e) => return eprintln!("Error: {}: {}", input, e),
+    };
+    let groups = match parse_groups(&data) {
+        Ok(groups) => groups,
+        Err(e) => return eprintln!("Error: {}", e),
+    };
+    if args.iter().any(|x| x == "--report") {
+        let threshold = match args.iter().position(|x| x == "--threshold") {
+            Some(i) => match args.get(i + 1).and_then(|x| x.parse::<usize>().ok()) {
+                Some(threshold) => threshold,
+                None => return eprintln!("Error: --threshold needs a number"),
+            },
+            None => 1,
+        };
+        return report(&groups, threshold);
+    }
+    if let Some(i) = args.iter().position(|x| x == "--reassign") {
+        return match args.get(i + 1).map(String::as_str) {
+            Some("group") => print_reassigned(&groups, Scope::Group),
+            Some("camp") => print_reassigned(&groups, Scope::Camp),
+            _ => eprintln!("Error: --reassign needs either group or camp"),
+        };
+    }
+
     let mut contain_count = 0;
     let mut overlap_count = 0;
-    for line in fs::read_to_string("data.txt").unwrap().lines() {
-        let pair = parse_pair(line);
-        if pair.0.fully_contains(&pair.1) || pair.1.fully_contains(&pair.0) {
+    let mut overlapping_pairs = 0;
+    let mut camp = IntervalSet::default();
+    for group in groups.iter() {
+        if group.container().is_some() {
             contain_count += 1;
         }
-        if pair.0.overlaps(&pair.1) {
+        let pairs = group.overlapping_pairs();
+        if pairs > 0 {
             overlap_count += 1;
         }
+        overlapping_pairs += pairs;
+        camp = camp.union(&group.members.iter().map(|x| x.sections).collect());
     }
     println!("Total fully contained: {}", contain_count);
     println!("Total overlaps: {}", overlap_count);
+    println!("Overlapping pairs within groups: {}", overlapping_pairs);
+    println!(
+        "Sections covered: {} in {} range(s), with {} gap(s)",
+        camp.len(),
+        camp.ranges().len(),
+        camp.gaps().ranges().len()
+    );
 }
@@ -0,0 +1,146 @@
+///
+/// Re-assignment optimiser.
+///
+/// Proposes new assignments so that no two elves share a section, while covering exactly the same
+/// sections as before. Each elf still gets a single contiguous range, so every covered range of
+/// the camp needs at least one elf. Elves are handed out to the ranges so that the largest
+/// workload is as small as possible, and each range is then split as evenly as it can be. Elves
+/// keep their original order along the camp, so most elves stay close to their old sections.
+///
+use std::{collections::BinaryHeap, fmt};
+
+use crate::group::Group;
+use crate::sections::{Bound, IntervalSet, Sections};
+
+///
+/// Whether overlaps are removed within each group, or across the whole camp
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Group,
+    Camp,
+}
+
+///
+/// Errors raised when a set of elves cannot be re-assigned without overlaps.
+///
+/// The line is the group's line in the input, or None when working across the whole camp.
+///
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReassignError {
+    TooFewElves { line: Option<usize>, ranges: usize, elves: usize },
+    TooManyElves { line: Option<usize>, sections: u128, elves: usize },
+}
+
+impl fmt::Display for ReassignError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (line, message) = match self {
+            ReassignError::TooFewElves { line, ranges, elves } => (
+                line, format!("{} elves cannot cover {} separate ranges", elves, ranges),
+            ),
+            ReassignError::TooManyElves { line, sections, elves } => (
+                line, format!("{} elves cannot each get one of only {} sections", elves, sections),
+            ),
+        };
+        match line {
+            Some(line) => write!(f, "line {}: {}", line, message),
+            None => write!(f, "camp: {}", message),
+        }
+    }
+}
+
+///
+/// Split the sections covered by a set of assignments into one disjoint range per assignment.
+///
+/// The new ranges are returned in the same order as the assignments they replace.
+///
+fn split<T: Bound>(assignments: &[Sections<T>], line: Option<usize>) -> Result<Vec<Sections<T>>, ReassignError> {
+    let covered: IntervalSet<T> = assignments.iter().copied().collect();
+    let ranges = covered.ranges();
+    let elves = assignments.len();
+    if elves < ranges.len() {
+        return Err(ReassignError::TooFewElves { line, ranges: ranges.len(), elves });
+    }
+    if elves as u128 > covered.len() {
+        return Err(ReassignError::TooManyElves { line, sections: covered.len(), elves });
+    }
+
+    // Give each range one elf, then keep giving the next elf to the range with the heaviest
+    // workload per elf
+    let mut counts: Vec<u128> = vec![1; ranges.len()];
+    let mut heaviest: BinaryHeap<(u128, usize)> = ranges.iter().map(|x| x.size()).zip(0..).collect();
+    for _ in ranges.len()..elves {
+        let (_, i) = heaviest.pop().unwrap();
+        counts[i] += 1;
+        heaviest.push((ranges[i].size().div_ceil(counts[i]), i));
+    }
+
+    let mut pieces: Vec<Sections<T>> = vec![];
+    for (range, count) in ranges.iter().zip(counts) {
+        let (size, extra) = (range.size() / count, range.size() % count);
+        let mut start = range.start;
+        for piece in 0..count {
+            let end = start.offset(size + u128::from(piece < extra) - 1).unwrap();
+            pieces.push(Sections { start, end });
+            if let Some(next) = end.next() {
+                start = next;
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0..elves).collect();
+    order.sort_by_key(|i| (assignments[*i].start, assignments[*i].end));
+    let mut assigned: Vec<Sections<T>> = assignments.to_vec();
+    for (i, piece) in order.into_iter().zip(pieces) {
+        assigned[i] = piece;
+    }
+    Ok(assigned)
+}
+
+///
+/// Propose new assignments for every group, keeping labels and the group layout.
+///
+/// Any group that cannot be re-assigned is left as it was, and the reasons are returned alongside
+/// the new groups. Across the whole camp it is all or nothing.
+///
+pub fn reassign<T: Bound>(groups: &[Group<T>], scope: Scope) -> (Vec<Group<T>>, Vec<ReassignError>) {
+    let mut reassigned: Vec<Group<T>> = groups.to_vec();
+    let mut errors: Vec<ReassignError> = vec![];
+    match scope {
+        Scope::Group => {
+            for (i, group) in reassigned.iter_mut().enumerate() {
+                let sections: Vec<Sections<T>> = group.members.iter().map(|x| x.sections).collect();
+                match split(&sections, Some(i + 1)) {
+                    Ok(split_sections) => {
+                        for (member, sections) in group.members.iter_mut().zip(split_sections) {
+                            member.sections = sections;
+                        }
+                    }
+                    Err(e) => errors.push(e),
+                }
+            }
+        }
+        Scope::Camp => {
+            let sections: Vec<Sections<T>> = groups.iter()
+                .flat_map(|x| x.members.iter().map(|y| y.sections))
+                .collect();
+            match split(&sections, None) {
+                Ok(split_sections) => {
+                    let members = reassigned.iter_mut().flat_map(|x| x.members.iter_mut());
+                    for (member, sections) in members.zip(split_sections) {
+                        member.sections = sections;
+                    }
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+    }
+    (reassigned, errors)
+}
+
+///
+/// The largest number of sections given to any one elf
+///
+pub fn max_workload<T: Bound>(groups: &[Group<T>]) -> u128 {
+    groups.iter().flat_map(|x| x.members.iter()).map(|x| x.sections.size()).max().unwrap_or(0)
+}
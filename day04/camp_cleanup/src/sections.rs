@@ -0,0 +1,277 @@
+///
+/// Section ranges and sets of them.
+///
+/// Sections is a single inclusive range of section IDs, and IntervalSet is a normalised set of
+/// them supporting the usual set algebra. Both are generic over the integer type used for the
+/// bounds so they can be reused for any range based puzzle.
+///
+use std::{fmt, str::FromStr};
+
+///
+/// An integer type that can be used as a section bound
+///
+pub trait Bound: Copy + Ord + fmt::Debug + fmt::Display + FromStr {
+    ///
+    /// The next value up, if there is one
+    ///
+    fn next(self) -> Option<Self>;
+    ///
+    /// The next value down, if there is one
+    ///
+    fn prev(self) -> Option<Self>;
+    ///
+    /// The number of values in the inclusive range start..=end
+    ///
+    fn count(start: Self, end: Self) -> u128;
+    ///
+    /// The value a number of steps above this one, if it fits
+    ///
+    fn offset(self, steps: u128) -> Option<Self>;
+}
+
+macro_rules! impl_bound {
+    ($($t:ty),*) => {
+        $(
+            impl Bound for $t {
+                fn next(self) -> Option<Self> {
+                    self.checked_add(1)
+                }
+                fn prev(self) -> Option<Self> {
+                    self.checked_sub(1)
+                }
+                fn count(start: Self, end: Self) -> u128 {
+                    (end as i128 - start as i128 + 1) as u128
+                }
+                fn offset(self, steps: u128) -> Option<Self> {
+                    i128::try_from(steps).ok()
+                        .and_then(|x| x.checked_add(self as i128))
+                        .and_then(|x| Self::try_from(x).ok())
+                }
+            }
+        )*
+    };
+}
+
+impl_bound!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+///
+/// Errors raised when parsing assignments
+///
+#[derive(Debug, PartialEq, Eq)]
+pub enum SectionsError {
+    Empty,
+    MissingSeparator { entry: String },
+    InvalidBound { entry: String, bound: String },
+    Reversed { entry: String },
+    EmptyLabel { entry: String },
+}
+
+impl fmt::Display for SectionsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SectionsError::Empty => write!(f, "no assignments"),
+            SectionsError::MissingSeparator { entry } => write!(f, "{:?} is not a `start-end` range", entry),
+            SectionsError::InvalidBound { entry, bound } => write!(f, "{:?} has an invalid bound {:?}", entry, bound),
+            SectionsError::Reversed { entry } => write!(f, "{:?} starts after it ends", entry),
+            SectionsError::EmptyLabel { entry } => write!(f, "{:?} has an empty label", entry),
+        }
+    }
+}
+
+///
+/// Section struct housing the beginning and end sections for a given elf
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sections<T: Bound = u64> {
+    pub start: T,
+    pub end: T,
+}
+
+impl<T: Bound> Sections<T> {
+    ///
+    /// Create a new Sections instance from a `start-end` entry.
+    ///
+    /// The separator is the first `-` after the start, so negative bounds such as `-3--1` parse
+    /// when T is signed.
+    ///
+    pub fn new(data: &str) -> Result<Sections<T>, SectionsError> {
+        let entry = || data.to_string();
+        let (start, end) = match data.char_indices().skip(1).find(|(_, x)| *x == '-') {
+            Some((i, _)) => (&data[..i], &data[i + 1..]),
+            None => return Err(SectionsError::MissingSeparator { entry: entry() }),
+        };
+        let parse = |bound: &str| {
+            bound.trim().parse::<T>().map_err(|_| SectionsError::InvalidBound { entry: entry(), bound: bound.to_string() })
+        };
+        let (start, end) = (parse(start)?, parse(end)?);
+        match start <= end {
+            true => Ok(Sections { start, end }),
+            false => Err(SectionsError::Reversed { entry: entry() }),
+        }
+    }
+    ///
+    /// Check that this section fully contains another
+    ///
+    pub fn fully_contains(&self, oth: &Sections<T>) -> bool {
+        IntervalSet::from(*self).contains_set(&IntervalSet::from(*oth))
+    }
+    ///
+    /// Check that this section overlaps with another
+    ///
+    pub fn overlaps(&self, oth: &Sections<T>) -> bool {
+        IntervalSet::from(*self).overlaps(&IntervalSet::from(*oth))
+    }
+    ///
+    /// The number of sections covered
+    ///
+    pub fn size(&self) -> u128 {
+        T::count(self.start, self.end)
+    }
+}
+
+impl<T: Bound> fmt::Display for Sections<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+///
+/// IntervalSet, a set of sections stored as sorted, disjoint, non-adjacent ranges.
+///
+/// Overlapping and adjacent ranges are merged as they are added, so 2-4 and 5-6 are stored as 2-6.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntervalSet<T: Bound = u64> {
+    ranges: Vec<Sections<T>>,
+}
+
+impl<T: Bound> Default for IntervalSet<T> {
+    fn default() -> Self {
+        IntervalSet { ranges: vec![] }
+    }
+}
+
+impl<T: Bound> From<Sections<T>> for IntervalSet<T> {
+    fn from(sections: Sections<T>) -> Self {
+        IntervalSet::from_iter([sections])
+    }
+}
+
+impl<T: Bound> FromIterator<Sections<T>> for IntervalSet<T> {
+    ///
+    /// Build a set from any number of ranges, sorting and merging them
+    ///
+    fn from_iter<I: IntoIterator<Item = Sections<T>>>(iter: I) -> Self {
+        let mut sorted: Vec<Sections<T>> = iter.into_iter().filter(|x| x.start <= x.end).collect();
+        sorted.sort_by_key(|x| x.start);
+
+        let mut ranges: Vec<Sections<T>> = vec![];
+        for sections in sorted {
+            match ranges.last_mut() {
+                Some(last) if last.end.next().is_none_or(|x| sections.start <= x) => {
+                    last.end = last.end.max(sections.end);
+                }
+                _ => ranges.push(sections),
+            }
+        }
+        IntervalSet { ranges }
+    }
+}
+
+impl<T: Bound> IntervalSet<T> {
+    ///
+    /// The merged ranges in ascending order
+    ///
+    pub fn ranges(&self) -> &[Sections<T>] {
+        &self.ranges
+    }
+    ///
+    /// Check whether the set covers no sections at all
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+    ///
+    /// The total number of sections covered
+    ///
+    pub fn len(&self) -> u128 {
+        self.ranges.iter().map(|x| x.size()).sum()
+    }
+    ///
+    /// Sections covered by either set
+    ///
+    pub fn union(&self, oth: &IntervalSet<T>) -> IntervalSet<T> {
+        self.ranges.iter().chain(oth.ranges.iter()).copied().collect()
+    }
+    ///
+    /// Sections covered by both sets
+    ///
+    pub fn intersection(&self, oth: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut ranges: Vec<Sections<T>> = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < oth.ranges.len() {
+            let (a, b) = (self.ranges[i], oth.ranges[j]);
+            let start = a.start.max(b.start);
+            let end = a.end.min(b.end);
+            if start <= end {
+                ranges.push(Sections { start, end });
+            }
+            match a.end < b.end {
+                true => i += 1,
+                false => j += 1,
+            }
+        }
+        IntervalSet { ranges }
+    }
+    ///
+    /// Sections covered by this set but not the other
+    ///
+    pub fn difference(&self, oth: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut ranges: Vec<Sections<T>> = vec![];
+        let mut j = 0;
+        for range in self.ranges.iter() {
+            let mut start = Some(range.start);
+            while j < oth.ranges.len() && oth.ranges[j].end < range.start {
+                j += 1;
+            }
+            let mut k = j;
+            while let (Some(from), Some(cut)) = (start, oth.ranges.get(k)) {
+                if cut.start > range.end {
+                    break;
+                }
+                if cut.start > from {
+                    ranges.push(Sections { start: from, end: cut.start.prev().unwrap() });
+                }
+                start = cut.end.next();
+                k += 1;
+            }
+            if let Some(from) = start.filter(|x| *x <= range.end) {
+                ranges.push(Sections { start: from, end: range.end });
+            }
+        }
+        IntervalSet { ranges }
+    }
+    ///
+    /// The uncovered ranges between the lowest and highest covered sections
+    ///
+    pub fn gaps(&self) -> IntervalSet<T> {
+        IntervalSet {
+            ranges: self.ranges
+                .windows(2)
+                .map(|x| Sections { start: x[0].end.next().unwrap(), end: x[1].start.prev().unwrap() })
+                .collect(),
+        }
+    }
+    ///
+    /// Check that every section in the other set is also in this one
+    ///
+    pub fn contains_set(&self, oth: &IntervalSet<T>) -> bool {
+        oth.difference(self).is_empty()
+    }
+    ///
+    /// Check that the two sets share at least one section
+    ///
+    pub fn overlaps(&self, oth: &IntervalSet<T>) -> bool {
+        !self.intersection(oth).is_empty()
+    }
+}
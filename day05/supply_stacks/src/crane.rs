@@ -0,0 +1,179 @@
+///
+/// Crane models.
+///
+/// Each crane carries out a Command on the Stacks in its own way. Every crane is built from lifts,
+/// where a lift takes a block of crates off the top of one stack and puts it on another.
+///
+use crate::{Command, Stacks};
+
+///
+/// A crane capable of carrying out the rearrangement procedure
+///
+pub trait Crane {
+    ///
+    /// The name of the crane, as shown in the output
+    ///
+    fn name(&self) -> String;
+    ///
+    /// Carry out a single command, returning the number of lifts it took
+    ///
+    fn apply(&mut self, stacks: &mut Stacks, cmd: &Command) -> u32;
+    ///
+    /// Undo a single command, putting the crates back where they came from. Returns the number of
+    /// lifts it took.
+    ///
+    fn reverse(&mut self, stacks: &mut Stacks, cmd: &Command) -> u32;
+}
+
+///
+/// The CrateMover 9000, moving one crate at a time
+///
+pub struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn name(&self) -> String {
+        String::from("CrateMover 9000")
+    }
+    fn apply(&mut self, stacks: &mut Stacks, cmd: &Command) -> u32 {
+        for _ in 0..cmd.quantity {
+            let block = stacks.take(cmd.from, 1);
+            stacks.put(cmd.to, block);
+        }
+        cmd.quantity
+    }
+    fn reverse(&mut self, stacks: &mut Stacks, cmd: &Command) -> u32 {
+        for _ in 0..cmd.quantity {
+            let block = stacks.take(cmd.to, 1);
+            stacks.put(cmd.from, block);
+        }
+        cmd.quantity
+    }
+}
+
+///
+/// The CrateMover 9001, moving every crate in one go
+///
+pub struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn name(&self) -> String {
+        String::from("CrateMover 9001")
+    }
+    fn apply(&mut self, stacks: &mut Stacks, cmd: &Command) -> u32 {
+        let block = stacks.take(cmd.from, cmd.quantity);
+        stacks.put(cmd.to, block);
+        1
+    }
+    fn reverse(&mut self, stacks: &mut Stacks, cmd: &Command) -> u32 {
+        let block = stacks.take(cmd.to, cmd.quantity);
+        stacks.put(cmd.from, block);
+        1
+    }
+}
+
+///
+/// A crane that can only lift a limited number of crates at a time.
+///
+/// Each lift moves up to its capacity as a block, so a capacity of 1 behaves like the 9000.
+///
+pub struct CapacityCrane {
+    pub capacity: u32,
+}
+
+impl Crane for CapacityCrane {
+    fn name(&self) -> String {
+        format!("Capacity {} crane", self.capacity)
+    }
+    fn apply(&mut self, stacks: &mut Stacks, cmd: &Command) -> u32 {
+        let mut remaining = cmd.quantity;
+        let mut lifts = 0;
+        while remaining > 0 {
+            let block = stacks.take(cmd.from, remaining.min(self.capacity));
+            remaining -= block.len() as u32;
+            stacks.put(cmd.to, block);
+            lifts += 1;
+        }
+        lifts
+    }
+    fn reverse(&mut self, stacks: &mut Stacks, cmd: &Command) -> u32 {
+        // The last lift was the partial one, so it has to come off first
+        let mut remaining = cmd.quantity;
+        let mut lifts = 0;
+        while remaining > 0 {
+            let lift = match remaining % self.capacity {
+                0 => self.capacity,
+                partial => partial,
+            };
+            let block = stacks.take(cmd.to, lift);
+            stacks.put(cmd.from, block);
+            remaining -= lift;
+            lifts += 1;
+        }
+        lifts
+    }
+}
+
+///
+/// A crane that lifts every crate in one go, but turns the block upside down on the way
+///
+pub struct FlippingCrane;
+
+impl Crane for FlippingCrane {
+    fn name(&self) -> String {
+        String::from("Flipping crane")
+    }
+    fn apply(&mut self, stacks: &mut Stacks, cmd: &Command) -> u32 {
+        let mut block = stacks.take(cmd.from, cmd.quantity);
+        block.reverse();
+        stacks.put(cmd.to, block);
+        1
+    }
+    fn reverse(&mut self, stacks: &mut Stacks, cmd: &Command) -> u32 {
+        let mut block = stacks.take(cmd.to, cmd.quantity);
+        block.reverse();
+        stacks.put(cmd.from, block);
+        1
+    }
+}
+
+///
+/// Wraps another crane, keeping a running cost of the lifts and crate moves it has made
+///
+pub struct CostCounter {
+    pub crane: Box<dyn Crane>,
+    pub lifts: u64,
+    pub crate_moves: u64,
+}
+
+impl Crane for CostCounter {
+    fn name(&self) -> String {
+        self.crane.name()
+    }
+    fn apply(&mut self, stacks: &mut Stacks, cmd: &Command) -> u32 {
+        let lifts = self.crane.apply(stacks, cmd);
+        self.lifts += lifts as u64;
+        self.crate_moves += cmd.quantity as u64;
+        lifts
+    }
+    fn reverse(&mut self, stacks: &mut Stacks, cmd: &Command) -> u32 {
+        let lifts = self.crane.reverse(stacks, cmd);
+        self.lifts += lifts as u64;
+        self.crate_moves += cmd.quantity as u64;
+        lifts
+    }
+}
+
+///
+/// Build a crane from its command line name: 9000, 9001, flip, or capacity:K
+///
+pub fn from_name(name: &str) -> Option<Box<dyn Crane>> {
+    match name {
+        "9000" => Some(Box::new(CrateMover9000)),
+        "9001" => Some(Box::new(CrateMover9001)),
+        "flip" => Some(Box::new(FlippingCrane)),
+        oth => oth.strip_prefix("capacity:")
+            .and_then(|x| x.parse::<u32>().ok())
+            .filter(|x| *x > 0)
+            .map(|capacity| Box::new(CapacityCrane { capacity }) as Box<dyn Crane>),
+    }
+}
@@ -0,0 +1,193 @@
+///
+/// Parser for the crate drawing at the top of the puzzle input.
+///
+/// Crates are matched to the stack whose id sits underneath them in the key row, so rows can be
+/// ragged (editors often strip trailing whitespace), stack ids can have any number of digits, and
+/// crate labels can be any length.
+///
+/// Stacks can be rendered back into the same format. Every column is as wide as its widest crate
+/// or stack id (and at least 3, as in the puzzle), so the output always parses back to the same
+/// stacks.
+///
+use std::{collections::BTreeMap, fmt};
+
+///
+/// What was wrong with a drawing
+///
+#[derive(Debug, PartialEq, Eq)]
+pub enum DrawingErrorKind {
+    MissingKeyRow,
+    InvalidStackId(String),
+    DuplicateStackId(u32),
+    UnterminatedCrate,
+    EmptyCrate,
+    UnexpectedCharacter(char),
+    UnalignedCrate(String),
+    OverlappingCrates(u32),
+    FloatingCrate(String),
+}
+
+///
+/// An error in a drawing, along with where it was found.
+///
+/// Lines and columns are numbered from 1, with line 1 being the top of the drawing.
+///
+#[derive(Debug, PartialEq, Eq)]
+pub struct DrawingError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: DrawingErrorKind,
+}
+
+impl fmt::Display for DrawingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            DrawingErrorKind::MissingKeyRow => write!(f, "the drawing has no stack key row"),
+            DrawingErrorKind::InvalidStackId(id) => write!(f, "{:?} is not a stack id", id),
+            DrawingErrorKind::DuplicateStackId(id) => write!(f, "stack {} appears more than once", id),
+            DrawingErrorKind::UnterminatedCrate => write!(f, "crate is missing its closing ]"),
+            DrawingErrorKind::EmptyCrate => write!(f, "crate has no label"),
+            DrawingErrorKind::UnexpectedCharacter(x) => write!(f, "unexpected {:?}", x),
+            DrawingErrorKind::UnalignedCrate(label) => write!(f, "crate [{}] is not above exactly one stack", label),
+            DrawingErrorKind::OverlappingCrates(id) => write!(f, "more than one crate on stack {} in this row", id),
+            DrawingErrorKind::FloatingCrate(label) => write!(f, "crate [{}] has nothing underneath it", label),
+        }
+    }
+}
+
+///
+/// A token in a row, with its first and last columns (0 based, inclusive)
+///
+struct Token<'a> {
+    start: usize,
+    end: usize,
+    text: &'a str,
+}
+
+///
+/// Split the key row into whitespace separated tokens
+///
+fn key_tokens(line: &str) -> Vec<Token<'_>> {
+    let mut tokens: Vec<Token> = vec![];
+    let mut start: Option<(usize, usize)> = None;
+    for (column, (i, x)) in line.char_indices().chain([(line.len(), ' ')]).enumerate() {
+        match (x.is_whitespace(), start) {
+            (false, None) => start = Some((column, i)),
+            (true, Some((first, from))) => {
+                tokens.push(Token { start: first, end: column - 1, text: &line[from..i] });
+                start = None;
+            }
+            _ => (),
+        }
+    }
+    tokens
+}
+
+///
+/// Split a row of crates into `[label]` tokens, with the label as the token text
+///
+fn crate_tokens(line: &str, line_number: usize) -> Result<Vec<Token<'_>>, DrawingError> {
+    let error = |column: usize, kind: DrawingErrorKind| DrawingError { line: line_number, column: column + 1, kind };
+    let mut tokens: Vec<Token> = vec![];
+    let mut characters = line.char_indices().enumerate();
+    while let Some((column, (i, x))) = characters.next() {
+        match x {
+            '[' => {
+                let (end, close) = characters.by_ref()
+                    .find(|(_, (_, y))| *y == ']' || *y == '[' || y.is_whitespace())
+                    .filter(|(_, (_, y))| *y == ']')
+                    .map(|(end, (close, _))| (end, close))
+                    .ok_or_else(|| error(column, DrawingErrorKind::UnterminatedCrate))?;
+                if close == i + 1 {
+                    return Err(error(column, DrawingErrorKind::EmptyCrate));
+                }
+                tokens.push(Token { start: column, end, text: &line[i + 1..close] });
+            }
+            x if x.is_whitespace() => (),
+            x => return Err(error(column, DrawingErrorKind::UnexpectedCharacter(x))),
+        }
+    }
+    Ok(tokens)
+}
+
+///
+/// Parse a drawing into stacks of crates keyed on their stack id, each listed bottom to top
+///
+pub fn parse(data: &str) -> Result<BTreeMap<u32, Vec<String>>, DrawingError> {
+    let lines: Vec<&str> = data.lines().collect();
+    let key_line = match lines.iter().rposition(|x| !x.trim().is_empty()) {
+        Some(key_line) => key_line,
+        None => return Err(DrawingError { line: 1, column: 1, kind: DrawingErrorKind::MissingKeyRow }),
+    };
+
+    let mut stacks: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+    let keys = key_tokens(lines[key_line]);
+    for key in keys.iter() {
+        let error = |kind| DrawingError { line: key_line + 1, column: key.start + 1, kind };
+        let id = key.text.parse::<u32>().map_err(|_| error(DrawingErrorKind::InvalidStackId(key.text.to_string())))?;
+        if stacks.insert(id, vec![]).is_some() {
+            return Err(error(DrawingErrorKind::DuplicateStackId(id)));
+        }
+    }
+
+    // Work up from the bottom row, so each crate can be checked for something underneath it
+    for (height, line_index) in (0..key_line).rev().enumerate() {
+        let mut row: BTreeMap<u32, Token> = BTreeMap::new();
+        for token in crate_tokens(lines[line_index], line_index + 1)? {
+            let error = |kind| DrawingError { line: line_index + 1, column: token.start + 1, kind };
+            let mut under = keys.iter().filter(|x| x.start <= token.end && x.end >= token.start);
+            let id = match (under.next(), under.next()) {
+                (Some(key), None) => key.text.parse::<u32>().unwrap(),
+                _ => return Err(error(DrawingErrorKind::UnalignedCrate(token.text.to_string()))),
+            };
+            if stacks[&id].len() != height {
+                return Err(error(DrawingErrorKind::FloatingCrate(token.text.to_string())));
+            }
+            if row.contains_key(&id) {
+                return Err(error(DrawingErrorKind::OverlappingCrates(id)));
+            }
+            row.insert(id, token);
+        }
+        for (id, token) in row {
+            stacks.get_mut(&id).unwrap().push(token.text.to_string());
+        }
+    }
+    Ok(stacks)
+}
+
+///
+/// Centre some text in a column of the given width
+///
+fn centre(text: &str, width: usize) -> String {
+    let padding = width - text.chars().count();
+    format!("{}{}{}", " ".repeat(padding / 2), text, " ".repeat(padding - padding / 2))
+}
+
+///
+/// Render stacks into a drawing, with the key row at the bottom
+///
+pub fn render(stacks: &BTreeMap<u32, Vec<String>>) -> String {
+    let widths: Vec<usize> = stacks.iter()
+        .map(|(id, crates)| {
+            let widest = crates.iter().map(|x| x.chars().count() + 2).max().unwrap_or(0);
+            widest.max(id.to_string().len()).max(3)
+        })
+        .collect();
+    let height = stacks.values().map(|x| x.len()).max().unwrap_or(0);
+
+    let mut lines: Vec<String> = vec![];
+    for row in (0..height).rev() {
+        let cells: Vec<String> = stacks.values()
+            .zip(widths.iter())
+            .map(|(crates, width)| match crates.get(row) {
+                Some(label) => centre(&format!("[{}]", label), *width),
+                None => " ".repeat(*width),
+            })
+            .collect();
+        lines.push(cells.join(" "));
+    }
+    let keys: Vec<String> = stacks.keys().zip(widths.iter()).map(|(id, width)| centre(&id.to_string(), *width)).collect();
+    lines.push(keys.join(" "));
+    lines.join("\n")
+}
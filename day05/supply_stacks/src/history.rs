@@ -0,0 +1,123 @@
+///
+/// Replay history for the rearrangement procedure.
+///
+/// Every applied command is recorded along with the block of crates it took and the block it put
+/// down. That is enough to undo or redo a step without knowing which crane made it, so the stacks
+/// can be moved to any point in the procedure without re-parsing the input.
+///
+use crate::crane::Crane;
+use crate::{Command, CommandErrorKind, Stacks};
+
+///
+/// A single applied command
+///
+struct Step {
+    cmd: Command,
+    taken: Vec<String>,
+    placed: Vec<String>,
+}
+
+///
+/// History struct, the stacks at the current step and every step recorded so far
+///
+pub struct History {
+    stacks: Stacks,
+    steps: Vec<Step>,
+    position: usize,
+}
+
+impl History {
+    ///
+    /// Start recording from the given stacks
+    ///
+    pub fn new(stacks: Stacks) -> History {
+        History { stacks, steps: vec![], position: 0 }
+    }
+    ///
+    /// The stacks at the current step
+    ///
+    pub fn stacks(&self) -> &Stacks {
+        &self.stacks
+    }
+    ///
+    /// The current step, where 0 is the starting drawing
+    ///
+    pub fn position(&self) -> usize {
+        self.position
+    }
+    ///
+    /// The number of recorded steps
+    ///
+    pub fn steps(&self) -> usize {
+        self.steps.len()
+    }
+    ///
+    /// Apply a command with a crane and record it.
+    ///
+    /// Applying a command after undoing some steps discards the undone steps.
+    ///
+    pub fn apply(&mut self, crane: &mut dyn Crane, cmd: &Command) -> Result<(), CommandErrorKind> {
+        let taken = self.stacks.top(cmd.from, cmd.quantity).to_vec();
+        self.stacks.apply(crane, cmd)?;
+        let placed = self.stacks.top(cmd.to, cmd.quantity).to_vec();
+        self.steps.truncate(self.position);
+        self.steps.push(Step { cmd: cmd.clone(), taken, placed });
+        self.position += 1;
+        Ok(())
+    }
+    ///
+    /// Step back one command, returning false at the start
+    ///
+    pub fn undo(&mut self) -> bool {
+        if self.position == 0 {
+            return false;
+        }
+        self.position -= 1;
+        let step = &self.steps[self.position];
+        self.stacks.take(step.cmd.to, step.cmd.quantity);
+        self.stacks.put(step.cmd.from, step.taken.clone());
+        true
+    }
+    ///
+    /// Step forward one recorded command, returning false at the end
+    ///
+    pub fn redo(&mut self) -> bool {
+        if self.position == self.steps.len() {
+            return false;
+        }
+        let step = &self.steps[self.position];
+        self.stacks.take(step.cmd.from, step.cmd.quantity);
+        self.stacks.put(step.cmd.to, step.placed.clone());
+        self.position += 1;
+        true
+    }
+    ///
+    /// Move to a given step, stopping at the start or end of the history
+    ///
+    pub fn seek(&mut self, step: usize) {
+        while self.position > step && self.undo() {}
+        while self.position < step && self.redo() {}
+    }
+    ///
+    /// Every step after which a crate label arrived on top of a stack, where 0 is the starting
+    /// drawing
+    ///
+    pub fn on_top(&mut self, label: &str, stack: u32) -> Vec<usize> {
+        let current = self.position;
+        let mut steps: Vec<usize> = vec![];
+        let mut was_on_top = false;
+        self.seek(0);
+        loop {
+            let is_on_top = self.stacks.top(stack, 1).first().is_some_and(|x| x == label);
+            if is_on_top && !was_on_top {
+                steps.push(self.position);
+            }
+            was_on_top = is_on_top;
+            if !self.redo() {
+                break;
+            }
+        }
+        self.seek(current);
+        steps
+    }
+}
@@ -1,76 +1,212 @@
 /// 
 /// AOC 2022 Day 5
 ///
-use std::{fs,collections::HashMap};
+/// Runs the procedure with the CrateMover 9000 and then the 9001. Pass `--crane <name>` to use a
+/// single crane instead (9000, 9001, flip or capacity:K), and `--cost` to report the number of
+/// lifts and crate moves it took. `--dry-run` checks the whole procedure without moving anything,
+/// and lists every step that could not be carried out. `--input <file>` reads something other
+/// than data.txt.
+///
+/// The procedure can also be replayed with the chosen crane (the 9000 by default): `--step N`
+/// prints every stack after step N, `--on-top <crate> <stack>` lists the steps after which a crate
+/// reached the top of a stack, and `--replay` steps through the procedure interactively.
+/// `--show` prints the full drawing of the stacks at the end of the procedure.
+///
+/// `--reverse` treats the drawing as the state after the procedure, and prints the starting
+/// drawing followed by the procedure, ready to be run forwards again.
+///
+/// `--target <file>` plans a short procedure from the drawing to the drawing in another file, and
+/// `--tops <crates>` plans one that just leaves the given crates on top (`CMZ` or `[C][M][Z]`).
+/// The plan is printed as a full input, using the chosen crane, with no stack taller than
+/// `--max-height N` (unlimited by default), keeping the best `--beam N` states (200 by default) at
+/// each step of the search.
+///
+use std::{env, fmt, fs, io, collections::BTreeMap};
+
+mod crane;
+mod drawing;
+mod history;
+mod planner;
+
+use crane::{Crane, CostCounter, CrateMover9000, CrateMover9001};
+use drawing::DrawingError;
+use history::History;
+use planner::Goal;
 
 /// 
 /// Command struct, stores the command requirements for moving x stacks from a to b
 ///
+#[derive(Debug, Clone)]
 struct Command {
     quantity: u32,
-    from: char,
-    to: char,
+    from: u32,
+    to: u32,
+}
+/// 
+/// The reasons a command can fail
+///
+#[derive(Debug, PartialEq, Eq)]
+enum CommandErrorKind {
+    Malformed,
+    UnknownStack(u32),
+    NotEnoughCrates { stack: u32, needed: u32, available: usize },
+    Inconsistent { stack: u32, needed: u32, available: usize },
+}
+/// 
+/// A command that could not be carried out, with the line of the input it came from
+///
+#[derive(Debug, PartialEq, Eq)]
+struct CommandError {
+    line: usize,
+    command: String,
+    kind: CommandErrorKind,
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {} ({:?}): ", self.line, self.command)?;
+        match &self.kind {
+            CommandErrorKind::Malformed => write!(f, "expected `move <n> from <stack> to <stack>`"),
+            CommandErrorKind::UnknownStack(id) => write!(f, "there is no stack {}", id),
+            CommandErrorKind::NotEnoughCrates { stack, needed, available } => write!(
+                f, "cannot move {} crate(s) from stack {}, it only has {}", needed, stack, available
+            ),
+            CommandErrorKind::Inconsistent { stack, needed, available } => write!(
+                f, "the final state is inconsistent, this step put {} crate(s) on stack {} but it only has {}",
+                needed, stack, available
+            ),
+        }
+    }
 }
 /// 
-/// Stacks struct, houses the state of all of the stacks
+/// Stacks struct, houses the state of all of the stacks, keyed on their stack id.
+///
+/// Each stack holds its crate labels from bottom to top.
 ///
+#[derive(Debug, Clone)]
 struct Stacks {
-    stacks: HashMap<char, Vec<char>>,
+    stacks: BTreeMap<u32, Vec<String>>,
 }
 
 impl Stacks {
     /// 
-    /// Create a new Stacks instance
+    /// Create a new Stacks instance from the drawing in the input data
     ///
-    fn new(data: &str) -> Stacks {
-        let mut stacks: HashMap<char, Vec<char>> = HashMap::new();
-        let mut data_iterator = data.lines().rev();
-
-        let keys = data_iterator.next().unwrap();
-        let indices: Vec<usize> = keys.chars().enumerate()
-            .filter(|(_, x)| *x != ' ')
-            .map(|(i, _)| i)
-            .collect();
-
-        for ind in indices.iter() {
-            let vec_vals: Vec<char> = data_iterator.clone()
-                .map(|x| x.chars().nth(*ind).unwrap())
-                .filter(|x| *x != ' ')
-                .collect();
-            stacks.insert(keys.chars().nth(*ind).unwrap(), vec_vals);
+    fn new(data: &str) -> Result<Stacks, DrawingError> {
+        Ok(Stacks { stacks: drawing::parse(data)? })
+    }
+    /// 
+    /// Lift a block of crates off the top of a stack, keeping their order
+    ///
+    fn take(&mut self, from: u32, quantity: u32) -> Vec<String> {
+        let stack = self.stacks.get_mut(&from).unwrap();
+        stack.split_off(stack.len() - quantity as usize)
+    }
+    /// 
+    /// The top crates of a stack, up to the given quantity, from bottom to top
+    ///
+    fn top(&self, stack: u32, quantity: u32) -> &[String] {
+        match self.stacks.get(&stack) {
+            Some(crates) => &crates[crates.len().saturating_sub(quantity as usize)..],
+            None => &[],
         }
-
-        Stacks { stacks }
     }
     /// 
-    /// Move crates using the cratemover 9000
+    /// Put a block of crates on top of a stack
     ///
-    fn cratemover_9000(&mut self, cmd: &Command) {
-        for _ in (0..cmd.quantity).collect::<Vec<u32>>() {
-            let val = self.stacks.get_mut(&cmd.from).unwrap().pop().unwrap();
-            self.stacks.get_mut(&cmd.to).unwrap().push(val);
+    fn put(&mut self, to: u32, mut block: Vec<String>) {
+        self.stacks.get_mut(&to).unwrap().append(&mut block);
+    }
+    /// 
+    /// Check that a command can be carried out, given the height of each stack
+    ///
+    fn check(heights: &BTreeMap<u32, usize>, cmd: &Command) -> Result<(), CommandErrorKind> {
+        let available = *heights.get(&cmd.from).ok_or(CommandErrorKind::UnknownStack(cmd.from))?;
+        if !heights.contains_key(&cmd.to) {
+            return Err(CommandErrorKind::UnknownStack(cmd.to));
+        }
+        match available >= cmd.quantity as usize {
+            true => Ok(()),
+            false => Err(CommandErrorKind::NotEnoughCrates { stack: cmd.from, needed: cmd.quantity, available }),
         }
     }
     /// 
-    /// Move crates using the cratemover 9001
+    /// The number of crates on each stack
     ///
-    fn cratemover_9001(&mut self, cmd: &Command) {
-        let len = self.stacks.get(&cmd.from).unwrap().len() as u32;
-        let mut vals = self.stacks.get_mut(&cmd.from).unwrap().split_off((len - cmd.quantity) as usize);
-
-        self.stacks.get_mut(&cmd.to).unwrap().append(&mut vals);
+    fn heights(&self) -> BTreeMap<u32, usize> {
+        self.stacks.iter().map(|(id, stack)| (*id, stack.len())).collect()
+    }
+    /// 
+    /// Carry out a command with the given crane.
+    ///
+    /// The command is checked before anything moves, so the stacks are left untouched if it fails.
+    ///
+    fn apply(&mut self, crane: &mut dyn Crane, cmd: &Command) -> Result<(), CommandErrorKind> {
+        Stacks::check(&self.heights(), cmd)?;
+        crane.apply(self, cmd);
+        Ok(())
+    }
+    /// 
+    /// Undo a command with the given crane, working back from a later state.
+    ///
+    /// The command must have put its crates on top of the destination stack, so that stack needs
+    /// at least as many crates as were moved.
+    ///
+    fn unapply(&mut self, crane: &mut dyn Crane, cmd: &Command) -> Result<(), CommandErrorKind> {
+        let inverse = Command { quantity: cmd.quantity, from: cmd.to, to: cmd.from };
+        Stacks::check(&self.heights(), &inverse)?;
+        crane.reverse(self, cmd);
+        Ok(())
+    }
+    /// 
+    /// Check a whole procedure without moving any crates, returning every step that would fail.
+    ///
+    /// Only the stack heights matter, so they are tracked on their own. Failed steps are skipped,
+    /// just as apply would leave the stacks untouched.
+    ///
+    fn dry_run(&self, procedure: &str, first_line: usize) -> Vec<CommandError> {
+        let mut heights = self.heights();
+        let mut errors: Vec<CommandError> = vec![];
+        for (i, line) in procedure.lines().enumerate() {
+            let result = Command::new(line).and_then(|cmd| {
+                Stacks::check(&heights, &cmd)?;
+                *heights.get_mut(&cmd.from).unwrap() -= cmd.quantity as usize;
+                *heights.get_mut(&cmd.to).unwrap() += cmd.quantity as usize;
+                Ok(())
+            });
+            if let Err(kind) = result {
+                errors.push(CommandError { line: first_line + i, command: line.to_string(), kind });
+            }
+        }
+        errors
     }
     /// 
     /// Print the top crate label in each of the stacks
     ///
     fn print_stack_heads(&self) {
-        let mut keys = self.stacks.keys().map(|x| x.clone()).collect::<Vec<char>>();
-        keys.sort();
         print!("Stack Heads: ");
-        for key in keys {
-            print!("[{:#?}]", self.stacks.get(&key).unwrap().last().unwrap());
+        for stack in self.stacks.values() {
+            print!("[{}]", stack.last().map_or(" ", String::as_str));
         }
-        print!("\n");
+        println!();
+    }
+}
+
+impl fmt::Display for Stacks {
+    /// 
+    /// Write the stacks out as a drawing in the puzzle input format
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", drawing::render(&self.stacks))
+    }
+}
+
+impl fmt::Display for Command {
+    /// 
+    /// Write the command back out in the input format
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "move {} from {} to {}", self.quantity, self.from, self.to)
     }
 }
 
@@ -78,48 +214,234 @@ impl Command {
     /// 
     /// Parse a new command from a line of the input data
     ///
-    fn new(data: &str) -> Command {
-        let split_data: Vec<&str> = data.split(' ').into_iter().enumerate()
-            .filter(|(i, _)| i % 2 != 0)
-            .map(|(_, x)| x)
-            .collect();
-        Command { 
-            quantity: split_data[0].parse::<u32>().unwrap(),
-            from: split_data[1].parse::<char>().unwrap(),
-            to: split_data[2].parse::<char>().unwrap()
+    fn new(data: &str) -> Result<Command, CommandErrorKind> {
+        let split_data: Vec<&str> = data.split_whitespace().collect();
+        match split_data[..] {
+            ["move", quantity, "from", from, "to", to] => Ok(Command {
+                quantity: quantity.parse::<u32>().map_err(|_| CommandErrorKind::Malformed)?,
+                from: from.parse::<u32>().map_err(|_| CommandErrorKind::Malformed)?,
+                to: to.parse::<u32>().map_err(|_| CommandErrorKind::Malformed)?,
+            }),
+            _ => Err(CommandErrorKind::Malformed),
         }
     }
 }
 /// 
-/// Part 1 of the exercise
+/// Split the input into the drawing and the procedure, along with the line the procedure starts on
 ///
-fn part1() {
-    let data = fs::read_to_string("data.txt").unwrap();
-    let mut data_split = data.split("\n\n");
-    let mut stacks = Stacks::new(data_split.next().unwrap());
-
-    for cmd in data_split.next().unwrap().lines() {
-        stacks.cratemover_9000(&Command::new(&cmd));
+fn split_input(data: &str) -> (&str, &str, usize) {
+    let (drawing, procedure) = data.split_once("\n\n").unwrap_or((data, ""));
+    (drawing, procedure, drawing.lines().count() + 2)
+}
+/// 
+/// Run the whole procedure with a crane, recording every step.
+///
+/// Stops at the first command that cannot be carried out.
+///
+fn record(data: &str, crane: &mut dyn Crane) -> Result<History, String> {
+    let (drawing, procedure, first_line) = split_input(data);
+    let mut history = History::new(Stacks::new(drawing).map_err(|e| e.to_string())?);
+    for (i, line) in procedure.lines().enumerate() {
+        if let Err(kind) = Command::new(line).and_then(|cmd| history.apply(crane, &cmd)) {
+            return Err(CommandError { line: first_line + i, command: line.to_string(), kind }.to_string());
+        }
     }
-    stacks.print_stack_heads();
+    Ok(history)
 }
 /// 
-/// Part 2 of the exercise
+/// Run the whole procedure with a crane, and print the top of each stack, optionally followed by
+/// the full drawing
 ///
-fn part2() {
-    let data = fs::read_to_string("data.txt").unwrap();
-    let mut data_split = data.split("\n\n");
-    let mut stacks = Stacks::new(data_split.next().unwrap());
-
-    for cmd in data_split.next().unwrap().lines() {
-        stacks.cratemover_9001(&Command::new(&cmd));
+fn run(data: &str, crane: &mut dyn Crane, show: bool) {
+    match record(data, crane) {
+        Ok(history) => {
+            print!("{}: ", crane.name());
+            history.stacks().print_stack_heads();
+            if show {
+                println!("{}", history.stacks());
+            }
+        }
+        Err(e) => eprintln!("Error: {}", e),
+    }
+}
+/// 
+/// Step through a recorded procedure, reading commands from stdin
+///
+fn replay(history: &mut History) {
+    println!("Commands: next, back, goto <step>, on-top <crate> <stack>, quit");
+    history.seek(0);
+    loop {
+        println!("Step {} of {}", history.position(), history.steps());
+        println!("{}", history.stacks());
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+            return;
+        }
+        match line.split_whitespace().collect::<Vec<&str>>()[..] {
+            ["n"] | ["next"] => { history.redo(); }
+            ["b"] | ["back"] => { history.undo(); }
+            ["goto", step] => match step.parse::<usize>() {
+                Ok(step) => history.seek(step),
+                Err(_) => println!("{:?} is not a step", step),
+            },
+            ["on-top", label, stack] => match stack.parse::<u32>() {
+                Ok(stack) => println!("Steps: {:?}", history.on_top(label, stack)),
+                Err(_) => println!("{:?} is not a stack", stack),
+            },
+            ["q"] | ["quit"] => return,
+            _ => println!("Commands: next, back, goto <step>, on-top <crate> <stack>, quit"),
+        }
+    }
+}
+/// 
+/// Work back from a final drawing through the procedure to find the starting drawing
+///
+fn unwind(data: &str, crane: &mut dyn Crane) -> Result<Stacks, String> {
+    let (drawing, procedure, first_line) = split_input(data);
+    let mut stacks = Stacks::new(drawing).map_err(|e| e.to_string())?;
+    for (i, line) in procedure.lines().enumerate().collect::<Vec<_>>().into_iter().rev() {
+        let result = Command::new(line).and_then(|cmd| {
+            stacks.unapply(crane, &cmd).map_err(|e| match e {
+                CommandErrorKind::NotEnoughCrates { stack, needed, available } => {
+                    CommandErrorKind::Inconsistent { stack, needed, available }
+                }
+                oth => oth,
+            })
+        });
+        if let Err(kind) = result {
+            return Err(CommandError { line: first_line + i, command: line.to_string(), kind }.to_string());
+        }
+    }
+    Ok(stacks)
+}
+/// 
+/// Plan a procedure from the drawing in the input to a goal, and print it as a full input
+///
+fn plan(data: &str, goal: &Goal, crane: &mut dyn Crane, max_height: usize, width: usize) -> Result<(), String> {
+    let drawing = split_input(data).0;
+    let stacks = Stacks::new(drawing).map_err(|e| e.to_string())?;
+    let plan = planner::plan(&stacks, goal, crane, max_height, width).map_err(|e| e.to_string())?;
+    println!("{}\n", drawing.trim_end_matches('\n'));
+    for cmd in plan.commands.iter() {
+        println!("{}", cmd);
+    }
+    eprintln!("{}: {} steps, {} states searched", crane.name(), plan.commands.len(), plan.states);
+    Ok(())
+}
+/// 
+/// Check the procedure without running it, listing every step that would fail
+///
+fn dry_run(data: &str) {
+    let (drawing, procedure, first_line) = split_input(data);
+    let stacks = match Stacks::new(drawing) {
+        Ok(stacks) => stacks,
+        Err(e) => return eprintln!("Error: {}", e),
+    };
+    let errors = stacks.dry_run(procedure, first_line);
+    for e in errors.iter() {
+        println!("{}", e);
+    }
+    println!("{} of {} steps are invalid", errors.len(), procedure.lines().count());
+}
+/// 
+/// Get the value following an option on the command line, if the option was given
+///
+fn option_value<'a>(args: &'a [String], name: &str) -> Result<Option<&'a str>, String> {
+    match args.iter().position(|x| x == name) {
+        Some(i) => match args.get(i + 1) {
+            Some(value) => Ok(Some(value.as_str())),
+            None => Err(format!("{} needs a value", name)),
+        },
+        None => Ok(None),
     }
-    stacks.print_stack_heads();
 }
 /// 
 /// Entrypoint
 ///
 fn main() {
-    part1();
-    part2();
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = run_with_args(&args) {
+        eprintln!("Error: {}", e);
+    }
+}
+/// 
+/// Carry out whatever the command line asks for
+///
+fn run_with_args(args: &[String]) -> Result<(), String> {
+    let input = option_value(args, "--input")?.unwrap_or("data.txt");
+    let data = fs::read_to_string(input).map_err(|e| format!("{}: {}", input, e))?;
+    if args.iter().any(|x| x == "--dry-run") {
+        dry_run(&data);
+        return Ok(());
+    }
+    let mut cranes: Vec<Box<dyn Crane>> = match option_value(args, "--crane")? {
+        Some(name) => vec![
+            crane::from_name(name).ok_or("--crane needs one of 9000, 9001, flip or capacity:K")?
+        ],
+        None => vec![Box::new(CrateMover9000), Box::new(CrateMover9001)],
+    };
+
+    if args.iter().any(|x| x == "--reverse") {
+        let stacks = unwind(&data, cranes[0].as_mut())?;
+        println!("{}\n\n{}", stacks, split_input(&data).1.trim_end());
+        return Ok(());
+    }
+
+    let target = option_value(args, "--target")?;
+    let tops = option_value(args, "--tops")?;
+    if target.is_some() || tops.is_some() {
+        let goal = match (target, tops) {
+            (Some(target), None) => {
+                let target = fs::read_to_string(target).map_err(|e| format!("{}: {}", target, e))?;
+                Goal::Drawing(drawing::parse(split_input(&target).0).map_err(|e| e.to_string())?)
+            }
+            (None, Some(tops)) => Goal::Tops(planner::parse_tops(tops)),
+            _ => return Err(String::from("use only one of --target and --tops")),
+        };
+        let max_height = match option_value(args, "--max-height")? {
+            Some(x) => x.parse::<usize>().map_err(|_| format!("{:?} is not a height", x))?,
+            None => usize::MAX,
+        };
+        let width = match option_value(args, "--beam")? {
+            Some(x) => x.parse::<usize>().ok().filter(|x| *x > 0).ok_or(format!("{:?} is not a beam width", x))?,
+            None => 200,
+        };
+        return plan(&data, &goal, cranes[0].as_mut(), max_height, width);
+    }
+
+    let step = option_value(args, "--step")?;
+    let on_top = args.iter().position(|x| x == "--on-top").map(|i| (args.get(i + 1), args.get(i + 2)));
+    if step.is_some() || on_top.is_some() || args.iter().any(|x| x == "--replay") {
+        let mut history = record(&data, cranes[0].as_mut())?;
+        if let Some(step) = step {
+            history.seek(step.parse::<usize>().map_err(|_| format!("{:?} is not a step", step))?);
+            println!("Step {} of {}", history.position(), history.steps());
+            println!("{}", history.stacks());
+        }
+        if let Some(on_top) = on_top {
+            let (label, stack) = match on_top {
+                (Some(label), Some(stack)) => (label, stack.parse::<u32>().map_err(|_| format!("{:?} is not a stack", stack))?),
+                _ => return Err(String::from("--on-top needs a crate and a stack")),
+            };
+            println!("[{}] reached the top of stack {} after steps {:?}", label, stack, history.on_top(label, stack));
+        }
+        if args.iter().any(|x| x == "--replay") {
+            replay(&mut history);
+        }
+        return Ok(());
+    }
+
+    let show = args.iter().any(|x| x == "--show");
+    if args.iter().any(|x| x == "--cost") {
+        for crane in cranes.drain(..) {
+            let mut counter = CostCounter { crane, lifts: 0, crate_moves: 0 };
+            run(&data, &mut counter, show);
+            println!("Cost: {} lifts, {} crate moves", counter.lifts, counter.crate_moves);
+        }
+        return Ok(());
+    }
+    for crane in cranes.iter_mut() {
+        run(&data, crane.as_mut(), show);
+    }
+    Ok(())
 }
@@ -0,0 +1,344 @@
+///
+/// Procedure planner.
+///
+/// Searches for a short list of commands that takes the stacks from a starting drawing to a goal,
+/// either a whole drawing or just the crates that should end up on top of each stack. The search
+/// is a beam search, guided by how many crates are still out of place. Only a fixed number of the
+/// most promising states are kept after each step, so it runs in bounded memory but may miss the
+/// shortest plan, or any plan at all for a narrow beam.
+///
+/// Any crane can be planned for. Every crane only cares about how many crates it moves, so the
+/// order a block of crates ends up in is worked out once per quantity by running the crane on a
+/// scratch pair of stacks.
+///
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+};
+
+use crate::crane::Crane;
+use crate::{Command, Stacks};
+
+///
+/// What the planner is aiming for
+///
+pub enum Goal {
+    Drawing(BTreeMap<u32, Vec<String>>),
+    Tops(Vec<Option<String>>),
+}
+
+///
+/// Reasons a plan could not be found
+///
+#[derive(Debug, PartialEq, Eq)]
+pub enum PlanError {
+    DifferentStacks,
+    DifferentCrates,
+    MissingCrate(String),
+    TooTall { stack: u32, height: usize, limit: usize },
+    NoRoom { crates: usize, stacks: usize, limit: usize },
+    NotFound { states: usize },
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlanError::DifferentStacks => write!(f, "the goal does not have the same stacks as the drawing"),
+            PlanError::DifferentCrates => write!(f, "the goal does not have the same crates as the drawing"),
+            PlanError::MissingCrate(label) => write!(f, "there are not enough [{}] crates for the goal", label),
+            PlanError::TooTall { stack, height, limit } => write!(
+                f, "stack {} needs {} crates, more than the limit of {}", stack, height, limit
+            ),
+            PlanError::NoRoom { crates, stacks, limit } => write!(
+                f, "{} crates do not fit on {} stacks of at most {}", crates, stacks, limit
+            ),
+            PlanError::NotFound { states } => write!(f, "no plan found after searching {} states", states),
+        }
+    }
+}
+
+///
+/// A plan, along with how many states were searched to find it
+///
+pub struct Plan {
+    pub commands: Vec<Command>,
+    pub states: usize,
+}
+
+///
+/// Parse the crates wanted on top of each stack, either as one character per stack (`CMZ`) or in
+/// the same form as the stack heads output (`[C][M][ ]`), where `[ ]` is an empty stack
+///
+pub fn parse_tops(text: &str) -> Vec<Option<String>> {
+    let text = text.trim();
+    if !text.starts_with('[') {
+        return text.chars().filter(|x| !x.is_whitespace()).map(|x| Some(x.to_string())).collect();
+    }
+    text.split(']')
+        .filter_map(|x| x.trim().strip_prefix('['))
+        .map(|x| Some(x.trim().to_string()).filter(|x| !x.is_empty()))
+        .collect()
+}
+
+///
+/// Stacks as the planner sees them, with every label swapped for a number
+///
+type Layout = Vec<Vec<u16>>;
+
+///
+/// A layout during the search, with the stacks laid end to end and a GAP between each, so that
+/// it is cheap to copy, compare and hash
+///
+type State = Vec<u16>;
+
+const GAP: u16 = u16::MAX;
+
+///
+/// Split a state back into its stacks
+///
+fn stacks(state: &State) -> Vec<&[u16]> {
+    state.split(|x| *x == GAP).collect()
+}
+
+///
+/// The goal in terms of label numbers, in stack order
+///
+enum Target {
+    Drawing(Layout),
+    Tops(Vec<Option<u16>>),
+}
+
+impl Target {
+    ///
+    /// A guess at how far a state is from the goal, which is 0 only at the goal.
+    ///
+    /// For a drawing it is the number of crates that still have to move. For tops it is mostly an
+    /// estimate of the moves left, with ties broken by how deep the crates that are needed are
+    /// buried.
+    ///
+    fn distance(&self, state: &[&[u16]]) -> usize {
+        match self {
+            Target::Drawing(target) => state.iter()
+                .zip(target.iter())
+                .map(|(stack, goal)| stack.len() - stack.iter().zip(goal.iter()).take_while(|(x, y)| x == y).count())
+                .sum(),
+            Target::Tops(tops) => {
+                let done: Vec<bool> = state.iter().zip(tops.iter()).map(|(x, top)| x.last() == top.as_ref()).collect();
+                // Every stack with the wrong top needs one move to bring its crate over, plus the
+                // moves to uncover it first: one, or two when the covering crates have to go back
+                // on a stack that is already right. Each wanted crate gets its own copy, taking the
+                // easiest first, and a crate already on top of the stack that wants it is taken.
+                let mut wanted: BTreeMap<u16, usize> = BTreeMap::new();
+                let mut moves = 0;
+                for (top, _) in tops.iter().zip(done.iter()).filter(|(_, done)| !**done) {
+                    moves += 1;
+                    if let Some(label) = top {
+                        *wanted.entry(*label).or_insert(0) += 1;
+                    }
+                }
+                let mut depth = 0;
+                for (label, count) in wanted {
+                    let mut copies: Vec<(usize, usize)> = vec![];
+                    for (stack, done) in state.iter().zip(done.iter()) {
+                        for (position, _) in stack.iter().rev().enumerate().filter(|(_, x)| **x == label) {
+                            match (position, *done) {
+                                (0, true) => (),
+                                (0, false) => copies.push((0, 0)),
+                                (position, done) => copies.push((1 + usize::from(done), position)),
+                            }
+                        }
+                    }
+                    copies.sort();
+                    for (fetch, position) in copies.into_iter().take(count) {
+                        moves += fetch;
+                        depth += position;
+                    }
+                }
+                let total: usize = state.iter().map(|x| x.len()).sum();
+                moves * (total * tops.len() + 1) + depth
+            }
+        }
+    }
+}
+
+///
+/// Work out where each crate of a block ends up when a crane moves it, as indexes into the block
+///
+fn block_order(crane: &mut dyn Crane, quantity: u32) -> Vec<usize> {
+    let block: Vec<String> = (0..quantity).map(|x| x.to_string()).collect();
+    let mut scratch = Stacks { stacks: BTreeMap::from([(1, block), (2, vec![])]) };
+    crane.apply(&mut scratch, &Command { quantity, from: 1, to: 2 });
+    scratch.stacks[&2].iter().map(|x| x.parse::<usize>().unwrap()).collect()
+}
+
+///
+/// Turn the starting stacks and the goal into label numbers, checking the goal can be reached
+///
+fn prepare(start: &Stacks, goal: &Goal, max_height: usize) -> Result<(Layout, Target), PlanError> {
+    let mut labels: HashMap<&str, u16> = HashMap::new();
+    for label in start.stacks.values().flatten() {
+        let next = labels.len() as u16;
+        labels.entry(label.as_str()).or_insert(next);
+    }
+    let state: Layout = start.stacks.values()
+        .map(|x| x.iter().map(|y| labels[y.as_str()]).collect())
+        .collect();
+    let mut counts: HashMap<u16, usize> = HashMap::new();
+    for label in state.iter().flatten() {
+        *counts.entry(*label).or_insert(0) += 1;
+    }
+
+    // Stacks that should end up empty cannot hold any crates
+    let total: usize = state.iter().map(|x| x.len()).sum();
+    let stacks = match goal {
+        Goal::Drawing(_) => state.len(),
+        Goal::Tops(tops) => tops.iter().filter(|x| x.is_some()).count(),
+    };
+    if total > stacks.saturating_mul(max_height) {
+        return Err(PlanError::NoRoom { crates: total, stacks, limit: max_height });
+    }
+
+    let target = match goal {
+        Goal::Drawing(drawing) => {
+            if !drawing.keys().eq(start.stacks.keys()) {
+                return Err(PlanError::DifferentStacks);
+            }
+            if let Some((stack, crates)) = drawing.iter().find(|(_, x)| x.len() > max_height) {
+                return Err(PlanError::TooTall { stack: *stack, height: crates.len(), limit: max_height });
+            }
+            let target: Layout = drawing.values()
+                .map(|x| x.iter().map(|y| labels.get(y.as_str()).copied().ok_or(PlanError::DifferentCrates)).collect())
+                .collect::<Result<Layout, PlanError>>()?;
+            let mut remaining = counts.clone();
+            for label in target.iter().flatten() {
+                *remaining.get_mut(label).unwrap() -= 1;
+            }
+            if target.iter().map(|x| x.len()).sum::<usize>() != state.iter().map(|x| x.len()).sum::<usize>()
+                || remaining.values().any(|x| *x != 0) {
+                return Err(PlanError::DifferentCrates);
+            }
+            Target::Drawing(target)
+        }
+        Goal::Tops(tops) => {
+            if tops.len() != state.len() {
+                return Err(PlanError::DifferentStacks);
+            }
+            let mut remaining = counts.clone();
+            let mut target: Vec<Option<u16>> = vec![];
+            for top in tops {
+                target.push(match top {
+                    Some(label) => {
+                        let id = labels.get(label.as_str()).copied();
+                        match id.and_then(|x| remaining.get_mut(&x)).filter(|x| **x > 0) {
+                            Some(count) => *count -= 1,
+                            None => return Err(PlanError::MissingCrate(label.clone())),
+                        }
+                        id
+                    }
+                    None => None,
+                });
+            }
+            Target::Tops(target)
+        }
+    };
+    Ok((state, target))
+}
+
+///
+/// A state reached during the search, along with the node it came from and the command that got
+/// there
+///
+struct Candidate {
+    state: State,
+    distance: usize,
+    parent: usize,
+    cmd: Command,
+}
+
+///
+/// Search for a short list of commands that reaches the goal with the given crane.
+///
+/// No stack may grow taller than max_height. Only the best width states are kept at each step,
+/// and the search gives up once every crate could have been moved a few times over.
+///
+pub fn plan(start: &Stacks, goal: &Goal, crane: &mut dyn Crane, max_height: usize, width: usize) -> Result<Plan, PlanError> {
+    let (layout, target) = prepare(start, goal, max_height)?;
+    let ids: Vec<u32> = start.stacks.keys().copied().collect();
+    let total: usize = layout.iter().map(|x| x.len()).sum();
+    let orders: Vec<Vec<usize>> = (0..=total as u32).map(|x| block_order(crane, x)).collect();
+
+    // Each node kept in a beam records the node it came from and the command that got there,
+    // enough to read the plan back out once the goal is reached
+    let mut nodes: Vec<(usize, Option<Command>)> = vec![(0, None)];
+    let mut distance = target.distance(&layout.iter().map(|x| x.as_slice()).collect::<Vec<&[u16]>>());
+    let state: State = layout.join(&GAP);
+    let mut seen: HashSet<State> = HashSet::from([state.clone()]);
+    let mut beam: Vec<(State, usize)> = vec![(state, 0)];
+    let mut states = 0;
+
+    for _ in 0..=4 * total + ids.len() {
+        if distance == 0 {
+            let mut commands: Vec<Command> = vec![];
+            let mut current = beam[0].1;
+            while let (parent, Some(cmd)) = &nodes[current] {
+                commands.push(cmd.clone());
+                current = *parent;
+            }
+            commands.reverse();
+            return Ok(Plan { commands, states });
+        }
+
+        let mut candidates: Vec<Candidate> = vec![];
+        for (state, node) in beam.iter() {
+            let current = stacks(state);
+            for from in 0..current.len() {
+                for to in (0..current.len()).filter(|x| *x != from) {
+                    let movable = current[from].len().min(max_height.saturating_sub(current[to].len()));
+                    for (quantity, order) in orders.iter().enumerate().take(movable + 1).skip(1) {
+                        let (rest, block) = current[from].split_at(current[from].len() - quantity);
+                        let mut next: State = Vec::with_capacity(state.len());
+                        for (i, stack) in current.iter().enumerate() {
+                            if i > 0 {
+                                next.push(GAP);
+                            }
+                            match i {
+                                i if i == from => next.extend_from_slice(rest),
+                                i if i == to => {
+                                    next.extend_from_slice(stack);
+                                    next.extend(order.iter().map(|x| block[*x]));
+                                }
+                                _ => next.extend_from_slice(stack),
+                            }
+                        }
+                        candidates.push(Candidate {
+                            distance: target.distance(&stacks(&next)),
+                            state: next,
+                            parent: *node,
+                            cmd: Command { quantity: quantity as u32, from: ids[from], to: ids[to] },
+                        });
+                    }
+                }
+            }
+        }
+        states += candidates.len();
+
+        candidates.sort_by_key(|x| x.distance);
+        beam.clear();
+        for candidate in candidates {
+            if beam.len() == width {
+                break;
+            }
+            if seen.insert(candidate.state.clone()) {
+                if beam.is_empty() {
+                    distance = candidate.distance;
+                }
+                nodes.push((candidate.parent, Some(candidate.cmd)));
+                beam.push((candidate.state, nodes.len() - 1));
+            }
+        }
+        if beam.is_empty() {
+            break;
+        }
+    }
+    Err(PlanError::NotFound { states })
+}
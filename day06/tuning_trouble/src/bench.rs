@@ -0,0 +1,94 @@
+///
+/// Benchmark of the sliding window marker search against the original sort and dedup one.
+///
+/// `cargo run --release -- bench` searches a 4 MB transmission for each marker size, with the
+/// only marker right at the end, and checks both searches find the same one.
+///
+use std::time::Instant;
+
+use super::find_marker;
+
+const TRANSMISSION_SIZE: usize = 4_000_000;
+const CHARACTERS: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+
+///
+/// An endless run of letters drawn from the first `count` of the alphabet, the same every time
+/// for the same seed
+///
+fn letters(mut seed: u64, count: usize) -> impl Iterator<Item = char> {
+    std::iter::repeat_with(move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        CHARACTERS[(seed % count as u64) as usize] as char
+    })
+}
+
+///
+/// Generate a transmission with a single marker of the given size right at the end.
+///
+/// Everything before the marker is drawn from one fewer characters than the marker needs, so no
+/// earlier window can be a marker and the whole transmission has to be searched. The marker starts
+/// with an `a` and is padded with more of them, so it cannot be found early either.
+///
+fn generate_data(seed: u64, size: usize, num_chars: usize) -> String {
+    let mut data: String = letters(seed, num_chars - 1).take(size - 2 * num_chars).collect();
+    data.extend(std::iter::repeat_n('a', num_chars + 1));
+    data.extend(CHARACTERS[CHARACTERS.len() - num_chars + 1..].iter().map(|x| *x as char));
+    data.push('\n');
+    data
+}
+
+///
+/// The original marker search, sorting and deduping a copy of the window at every step.
+///
+fn naive_find_marker(transmission: &str, num_chars: usize) -> Option<(Vec<char>, usize)> {
+    let mut characters = transmission.chars();
+    let mut tracker = num_chars;
+    let mut items = (0..num_chars)
+        .map(|_| characters.next().unwrap())
+        .collect::<Vec<char>>();
+
+    for _ in 0..transmission.len() - num_chars {
+        let mut items_clone = items.clone();
+        items_clone.sort();
+        items_clone.dedup();
+        if items_clone.len() == num_chars {
+            break;
+        }
+        items.reverse();
+        items.pop();
+        items.reverse();
+        items.push(characters.next().unwrap());
+        tracker += 1;
+    }
+    match tracker == transmission.len() {
+        true => None,
+        false => Some((items, tracker)),
+    }
+}
+
+///
+/// Time a marker search that has to succeed, printing where the marker ends
+///
+fn time<F: FnOnce() -> Option<(Vec<char>, usize)>>(label: &str, f: F) -> (Vec<char>, usize) {
+    let start = Instant::now();
+    let result = f().unwrap();
+    println!("{:<24} {:>12} {:>10.2?}", label, result.1, start.elapsed());
+    result
+}
+
+///
+/// Run the benchmark
+///
+pub fn run() {
+    for num_chars in [4, 14] {
+        let data = generate_data(0x2022_0006 + num_chars as u64, TRANSMISSION_SIZE, num_chars);
+        println!("{} character markers, {} bytes", num_chars, data.len());
+        let naive = time("naive", || naive_find_marker(&data, num_chars));
+        let sliding = time("sliding window", || {
+            find_marker(&data, num_chars).ok().map(|x| (x.items, x.position))
+        });
+        assert_eq!(naive, sliding);
+    }
+}
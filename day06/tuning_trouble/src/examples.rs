@@ -0,0 +1,82 @@
+///
+/// Tests against the worked examples in puzzle.txt.
+///
+/// Every example is checked with find_marker, the framing decoder and the stream detector, for
+/// both start-of-packet and start-of-message markers.
+///
+use super::{find_marker, stream};
+use crate::framing::Decoder;
+
+///
+/// Each example transmission, with where its first packet and first message markers end
+///
+const EXAMPLES: &[(&str, usize, usize)] = &[
+    ("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 7, 19),
+    ("bvwbjplbgvbhsrlpgdmjqwftvncz", 5, 23),
+    ("nppdvjthqldpwncqszvftbrmjlhg", 6, 23),
+    ("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 10, 29),
+    ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 11, 26),
+];
+
+#[test]
+fn find_marker_examples() {
+    for (transmission, packet, message) in EXAMPLES {
+        assert_eq!(find_marker(transmission, 4).map(|x| x.position), Ok(*packet), "{}", transmission);
+        assert_eq!(find_marker(transmission, 14).map(|x| x.position), Ok(*message), "{}", transmission);
+    }
+}
+
+#[test]
+fn decoder_examples() {
+    for (transmission, packet, message) in EXAMPLES {
+        for (size, expected) in [(4, *packet), (14, *message)] {
+            let decoded = Decoder { sizes: vec![size] }.decode(transmission);
+            assert_eq!(decoded.frames.first().map(|x| x.payload_start), Some(expected), "{}", transmission);
+        }
+    }
+}
+
+#[test]
+fn stream_examples() {
+    for (transmission, packet, message) in EXAMPLES {
+        let found: Vec<stream::Marker> = stream::markers(transmission.as_bytes(), &[4, 14])
+            .collect::<Result<_, _>>()
+            .unwrap();
+        for (size, expected) in [(4, *packet), (14, *message)] {
+            let first = found.iter().find(|x| x.size == size).map(|x| x.position);
+            assert_eq!(first, Some(expected), "{}", transmission);
+        }
+    }
+}
+
+#[test]
+fn markers_agree() {
+    let transmission = "abcdabcdabcd";
+    let decoded = Decoder { sizes: vec![4] }.decode(transmission);
+    let streamed: Vec<usize> = stream::markers(transmission.as_bytes(), &[4]).map(|x| x.unwrap().position).collect();
+    let framed: Vec<usize> = decoded.frames.iter().map(|x| x.payload_start).collect();
+    assert_eq!(streamed, vec![4, 8, 12]);
+    assert_eq!(framed, streamed);
+}
+
+#[test]
+fn nested_decode() {
+    let decoded = Decoder { sizes: vec![14, 4] }.decode("aaabcdefghijklmnppqqrstuuvwxé\n");
+    assert_eq!((decoded.characters, decoded.bytes), (29, 30));
+    assert_eq!(decoded.preamble, "aa");
+    assert_eq!(decoded.frames.len(), 1);
+
+    let message = &decoded.frames[0];
+    assert_eq!(message.marker, "abcdefghijklmn");
+    assert_eq!((message.start, message.payload_start, message.end), (2, 16, 29));
+    assert_eq!((message.byte_start, message.byte_payload_start, message.byte_end), (2, 16, 30));
+    assert_eq!(message.payload, "ppqqrstuuvwxé");
+    assert_eq!(message.preamble.as_deref(), Some("ppq"));
+
+    let packets: Vec<(&str, &str, usize, usize, usize)> = message.frames.iter()
+        .map(|x| (x.marker.as_str(), x.payload.as_str(), x.start, x.payload_start, x.end))
+        .collect();
+    assert_eq!(packets, vec![("qrst", "u", 19, 23, 24), ("uvwx", "é", 24, 28, 29)]);
+    assert_eq!(message.frames[1].byte_end, 30);
+    assert_eq!(message.frames[1].preamble.as_deref(), None);
+}
@@ -0,0 +1,172 @@
+///
+/// Framing decoder.
+///
+/// Splits a transmission into frames, each made of a marker and the payload that follows it up
+/// to the next marker. Frames can be nested by giving more than one window size: with sizes 14
+/// and 4, the transmission is split into messages, and each message payload is split again into
+/// packets.
+///
+/// Markers are found with a Window, so they never overlap. Anything before the first marker at a
+/// level is kept as the preamble.
+///
+use serde::Serialize;
+
+use crate::{LastSeen, Window};
+
+///
+/// A single frame, with offsets in characters and bytes from the start of the transmission
+///
+#[derive(Debug, Serialize)]
+pub struct Frame {
+    pub size: usize,
+    pub marker: String,
+    pub payload: String,
+    pub start: usize,
+    pub payload_start: usize,
+    pub end: usize,
+    pub byte_start: usize,
+    pub byte_payload_start: usize,
+    pub byte_end: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preamble: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub frames: Vec<Frame>,
+}
+
+///
+/// Payload statistics for every frame of one window size
+///
+#[derive(Debug, Serialize)]
+pub struct LayerStats {
+    pub size: usize,
+    pub frames: usize,
+    pub payload_characters: usize,
+    pub shortest_payload: usize,
+    pub longest_payload: usize,
+    pub mean_payload: f64,
+}
+
+///
+/// A decoded transmission
+///
+#[derive(Debug, Serialize)]
+pub struct Decoded {
+    pub sizes: Vec<usize>,
+    pub characters: usize,
+    pub bytes: usize,
+    pub preamble: String,
+    pub frames: Vec<Frame>,
+    pub stats: Vec<LayerStats>,
+}
+
+///
+/// Decoder struct, the window size of each level of framing, outermost first
+///
+pub struct Decoder {
+    pub sizes: Vec<usize>,
+}
+
+impl Decoder {
+    ///
+    /// Decode a transmission into frames. Line terminators at the end are not part of it.
+    ///
+    pub fn decode(&self, transmission: &str) -> Decoded {
+        let transmission = transmission.trim_end_matches(['\n', '\r']);
+        let characters: Vec<(usize, char)> = transmission.char_indices().collect();
+        let text = Text { transmission, characters: &characters };
+        let (preamble, frames) = text.split(0, characters.len(), &self.sizes);
+        let stats = self.sizes.iter().enumerate().map(|(depth, size)| layer_stats(&frames, depth, *size)).collect();
+        Decoded {
+            sizes: self.sizes.clone(),
+            characters: characters.len(),
+            bytes: transmission.len(),
+            preamble: text.slice(0, preamble),
+            frames,
+            stats,
+        }
+    }
+}
+
+///
+/// The transmission, along with the byte offset of each of its characters
+///
+struct Text<'a> {
+    transmission: &'a str,
+    characters: &'a [(usize, char)],
+}
+
+impl Text<'_> {
+    ///
+    /// The byte offset of a character position, which may be the end of the transmission
+    ///
+    fn byte(&self, position: usize) -> usize {
+        self.characters.get(position).map_or(self.transmission.len(), |x| x.0)
+    }
+    ///
+    /// The text between two character positions
+    ///
+    fn slice(&self, start: usize, end: usize) -> String {
+        self.transmission[self.byte(start)..self.byte(end)].to_string()
+    }
+    ///
+    /// Split the characters between start and end into frames, using the first size and passing
+    /// the rest down to each payload. Returns where the preamble ends, along with the frames.
+    ///
+    fn split(&self, start: usize, end: usize, sizes: &[usize]) -> (usize, Vec<Frame>) {
+        let Some((&size, inner)) = sizes.split_first() else {
+            return (end, vec![]);
+        };
+        let mut markers: Vec<usize> = vec![];
+        let mut last_seen = LastSeen::new();
+        let mut window = Window::new(size, start);
+        for position in start..end {
+            let previous = last_seen.insert(self.characters[position].1, position);
+            if window.step(position, previous) == size {
+                markers.push(position + 1 - size);
+            }
+        }
+
+        let ends = markers.iter().skip(1).copied().chain([end]);
+        let frames = markers.iter().zip(ends).map(|(&marker, frame_end)| {
+            let payload_start = marker + size;
+            let (preamble, frames) = self.split(payload_start, frame_end, inner);
+            Frame {
+                size,
+                marker: self.slice(marker, payload_start),
+                payload: self.slice(payload_start, frame_end),
+                start: marker,
+                payload_start,
+                end: frame_end,
+                byte_start: self.byte(marker),
+                byte_payload_start: self.byte(payload_start),
+                byte_end: self.byte(frame_end),
+                preamble: (!inner.is_empty()).then(|| self.slice(payload_start, preamble)),
+                frames,
+            }
+        });
+        (markers.first().copied().unwrap_or(end), frames.collect())
+    }
+}
+
+///
+/// Gather the statistics for the frames at one depth of nesting
+///
+fn layer_stats(frames: &[Frame], depth: usize, size: usize) -> LayerStats {
+    let mut layer: Vec<&Frame> = frames.iter().collect();
+    for _ in 0..depth {
+        layer = layer.into_iter().flat_map(|x| x.frames.iter()).collect();
+    }
+    let lengths: Vec<usize> = layer.iter().map(|x| x.end - x.payload_start).collect();
+    let payload_characters: usize = lengths.iter().sum();
+    LayerStats {
+        size,
+        frames: lengths.len(),
+        payload_characters,
+        shortest_payload: lengths.iter().copied().min().unwrap_or(0),
+        longest_payload: lengths.iter().copied().max().unwrap_or(0),
+        mean_payload: match lengths.len() {
+            0 => 0.0,
+            count => payload_characters as f64 / count as f64,
+        },
+    }
+}
@@ -1,49 +1,266 @@
 ///
 /// AOC 2022 Day 6
 ///
-use std::fs;
-///
-/// Custom error for being out of characters
+use std::{
+    collections::HashMap,
+    env, fmt, fs,
+    io::{self, Read},
+    net::TcpStream,
+};
+
+mod bench;
+#[cfg(test)]
+mod examples;
+mod framing;
+mod stream;
 ///
-#[derive(Debug)]
-struct OutOfCharactersError;
+/// Custom error for being out of characters, with how far the search got.
 ///
-/// Find a given number of characters in a transmission
+/// Lengths and positions are in characters, and the longest run ends at longest_run_end.
 ///
-fn find_marker(
-    transmission: &str,
+#[derive(Debug, PartialEq, Eq)]
+struct OutOfCharactersError {
     num_chars: usize,
-) -> Result<(Vec<char>, usize), OutOfCharactersError> {
-    let mut characters = transmission.chars();
-    let mut tracker = num_chars;
-    let mut items = (0..num_chars)
-        .into_iter()
-        .map(|_| characters.next().unwrap())
-        .collect::<Vec<char>>();
+    scanned: usize,
+    longest_run: usize,
+    longest_run_end: usize,
+}
+
+impl fmt::Display for OutOfCharactersError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f, "no run of {} different characters in {} characters, the longest was {} ending at {}",
+            self.num_chars, self.scanned, self.longest_run, self.longest_run_end
+        )
+    }
+}
+///
+/// A marker found in a transmission.
+///
+/// The position is the number of characters read up to the end of the marker, and the byte
+/// offset is the same point in bytes.
+///
+#[derive(Debug, PartialEq, Eq)]
+struct Marker {
+    items: Vec<char>,
+    position: usize,
+    byte_offset: usize,
+}
+///
+/// The last position each character was seen at.
+///
+/// ASCII characters, which make up almost every transmission, are kept in a table; anything else
+/// falls back to a map.
+///
+pub(crate) struct LastSeen {
+    ascii: [Option<usize>; 128],
+    other: HashMap<char, usize>,
+}
 
-    for _ in 0..transmission.len() - num_chars {
-        let mut items_clone = items.clone();
-        items_clone.sort();
-        items_clone.dedup();
-        if items_clone.len() == num_chars {
-            break;
+impl LastSeen {
+    ///
+    /// Create a new, empty LastSeen instance
+    ///
+    pub(crate) fn new() -> LastSeen {
+        LastSeen { ascii: [None; 128], other: HashMap::new() }
+    }
+    ///
+    /// Record a character at a position, returning where it was last seen
+    ///
+    pub(crate) fn insert(&mut self, character: char, position: usize) -> Option<usize> {
+        match character.is_ascii() {
+            true => self.ascii[character as usize].replace(position),
+            false => self.other.insert(character, position),
         }
-        items.reverse();
-        items.pop();
-        items.reverse();
-        items.push(characters.next().unwrap());
-        tracker += 1;
     }
-    match tracker == transmission.len() {
-        true => Err(OutOfCharactersError),
-        false => Ok((items, tracker)),
+}
+///
+/// A run of different characters, searched for markers of one size.
+///
+/// This is what a marker is, wherever one is looked for. The run starts just after the last copy
+/// of the newest character, and once it is `size` characters long those characters are a marker.
+/// A marker uses up its characters, so the run starts again after it and markers of one size never
+/// overlap: `abcdabcdabcd` has three markers of size 4, ending at 4, 8 and 12.
+///
+pub(crate) struct Window {
+    pub(crate) size: usize,
+    start: usize,
+}
+
+impl Window {
+    ///
+    /// Create a new Window, with its run starting at a position
+    ///
+    pub(crate) fn new(size: usize, start: usize) -> Window {
+        Window { size, start }
+    }
+    ///
+    /// Take in the character at a position, given where that character was last seen. Returns the
+    /// length of the run ending with it, which is a marker when it equals the size.
+    ///
+    pub(crate) fn step(&mut self, position: usize, previous: Option<usize>) -> usize {
+        if let Some(previous) = previous {
+            self.start = self.start.max(previous + 1);
+        }
+        let run = position + 1 - self.start;
+        if run == self.size {
+            self.start = position + 1;
+        }
+        run
+    }
+}
+///
+/// Find the first marker of a given number of characters in a transmission.
+///
+/// Slides a Window over the transmission, so every character is looked at once. Line terminators
+/// at the end of the transmission are not part of it.
+///
+fn find_marker(transmission: &str, num_chars: usize) -> Result<Marker, OutOfCharactersError> {
+    let transmission = transmission.trim_end_matches(['\n', '\r']);
+    let mut last_seen = LastSeen::new();
+    let mut window = Window::new(num_chars, 0);
+    let (mut longest_run, mut longest_run_end) = (0, 0);
+    let mut scanned = 0;
+    for (position, (byte_offset, character)) in transmission.char_indices().enumerate() {
+        let run = window.step(position, last_seen.insert(character, position));
+        scanned = position + 1;
+        if run > longest_run {
+            (longest_run, longest_run_end) = (run, scanned);
+        }
+        if run == num_chars {
+            let byte_offset = byte_offset + character.len_utf8();
+            let mut items: Vec<char> = transmission[..byte_offset].chars().rev().take(num_chars).collect();
+            items.reverse();
+            return Ok(Marker { items, position: scanned, byte_offset });
+        }
+    }
+    Err(OutOfCharactersError { num_chars, scanned, longest_run, longest_run_end })
+}
+///
+/// Print the result of a marker search
+///
+fn print_marker(label: &str, result: Result<Marker, OutOfCharactersError>) {
+    match result {
+        Ok(marker) => println!(
+            "{}: {:?} ends at character {} (byte {})",
+            label, marker.items.iter().collect::<String>(), marker.position, marker.byte_offset
+        ),
+        Err(e) => println!("{}: {}", label, e),
+    }
+}
+///
+/// Open a stream to search: `-` for stdin, `tcp:<host>:<port>` for a socket, or a file
+///
+fn open_stream(source: &str) -> io::Result<Box<dyn Read>> {
+    if source == "-" {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+    match source.strip_prefix("tcp:") {
+        Some(address) => Ok(Box::new(TcpStream::connect(address)?)),
+        None => Ok(Box::new(fs::File::open(source)?)),
+    }
+}
+///
+/// Print every marker in a stream as it is found
+///
+fn print_stream(source: &str, sizes: &[usize]) -> Result<(), String> {
+    let reader = open_stream(source).map_err(|e| format!("{}: {}", source, e))?;
+    let mut markers = stream::markers(reader, sizes);
+    let mut counts: Vec<usize> = vec![0; sizes.len()];
+    for marker in markers.by_ref() {
+        let marker = marker.map_err(|e| format!("{}: {}", source, e))?;
+        println!("{} character marker at {} (byte {})", marker.size, marker.position, marker.byte_offset);
+        for (count, _) in counts.iter_mut().zip(sizes).filter(|(_, size)| **size == marker.size) {
+            *count += 1;
+        }
+    }
+    for (size, count) in sizes.iter().zip(counts) {
+        println!("{} markers of {} characters in {} characters", count, size, markers.position());
+    }
+    Ok(())
+}
+///
+/// Split a transmission into frames and print them, as JSON or as a summary
+///
+fn print_frames(transmission: &str, sizes: &[usize], json: bool) -> Result<(), String> {
+    let decoded = framing::Decoder { sizes: sizes.to_vec() }.decode(transmission);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&decoded).map_err(|e| e.to_string())?);
+        return Ok(());
+    }
+    println!("{} characters, {} before the first frame", decoded.characters, decoded.preamble.chars().count());
+    for frame in decoded.frames.iter() {
+        println!(
+            "{:?} at {}..{} (bytes {}..{}), {} character payload, {} inner frames",
+            frame.marker, frame.start, frame.end, frame.byte_start, frame.byte_end,
+            frame.end - frame.payload_start, frame.frames.len()
+        );
+    }
+    for layer in decoded.stats.iter() {
+        println!(
+            "{} character markers: {} frames, payloads of {} to {} characters, {:.1} on average",
+            layer.size, layer.frames, layer.shortest_payload, layer.longest_payload, layer.mean_payload
+        );
+    }
+    Ok(())
+}
+///
+/// Parse a comma separated list of window sizes
+///
+fn parse_sizes(sizes: &str) -> Result<Vec<usize>, String> {
+    match sizes.split(',').map(|x| x.trim().parse::<usize>()).collect::<Result<Vec<usize>, _>>() {
+        Ok(sizes) if !sizes.contains(&0) => Ok(sizes),
+        _ => Err(String::from("--sizes needs a comma separated list of window sizes")),
+    }
+}
+///
+/// Get the value following an option on the command line, if the option was given
+///
+fn option_value<'a>(args: &'a [String], name: &str) -> Result<Option<&'a str>, String> {
+    match args.iter().position(|x| x == name) {
+        Some(i) => match args.get(i + 1) {
+            Some(value) => Ok(Some(value.as_str())),
+            None => Err(format!("{} needs a value", name)),
+        },
+        None => Ok(None),
     }
 }
 ///
 /// Entrypoint
 ///
+/// Pass `bench` as the first argument to time the marker search against the original one on
+/// generated transmissions.
+///
+/// `--stream <source>` reads a file, stdin (`-`) or a socket (`tcp:<host>:<port>`) a chunk at a
+/// time and prints every marker, for each of the window sizes given by `--sizes` (4,14 by
+/// default). `--frames` splits the input into frames at the markers of each size in `--sizes`,
+/// outermost first (14,4 by default), and `--json` writes them out as JSON. `--input <file>`
+/// reads something other than data.txt.
+///
 fn main() {
-    let transmission = fs::read_to_string("data.txt").unwrap();
-    println!("First packet: {:#?}", find_marker(&transmission, 4));
-    println!("First message: {:#?}", find_marker(&transmission, 14));
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("bench") {
+        bench::run();
+        return;
+    }
+    if let Err(e) = run_with_args(&args) {
+        eprintln!("Error: {}", e);
+    }
+}
+///
+/// Carry out whatever the command line asks for
+///
+fn run_with_args(args: &[String]) -> Result<(), String> {
+    let sizes = option_value(args, "--sizes")?;
+    if let Some(source) = option_value(args, "--stream")? {
+        return print_stream(source, &parse_sizes(sizes.unwrap_or("4,14"))?);
+    }
+    let input = option_value(args, "--input")?.unwrap_or("data.txt");
+    let transmission = fs::read_to_string(input).map_err(|e| format!("{}: {}", input, e))?;
+    if args.iter().any(|x| x == "--frames") {
+        return print_frames(&transmission, &parse_sizes(sizes.unwrap_or("14,4"))?, args.iter().any(|x| x == "--json"));
+    }
+    print_marker("First packet", find_marker(&transmission, 4));
+    print_marker("First message", find_marker(&transmission, 14));
+    Ok(())
 }
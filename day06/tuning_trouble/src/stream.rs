@@ -0,0 +1,167 @@
+///
+/// Streaming marker detection.
+///
+/// A Detector is fed the transmission a character at a time, so it never needs more than a small
+/// table in memory however long the stream runs. It tracks any number of window sizes in the same
+/// pass, sharing a single table of where each character was last seen.
+///
+/// Each size has its own Window, so markers are the same ones a whole transmission would give.
+///
+/// The stream is decoded as UTF-8 as it is read, and a character split across two reads is put
+/// back together. As with a whole transmission, line terminators at the end are not part of it,
+/// so they are held back until another character shows they are not at the end.
+///
+use std::{
+    collections::VecDeque,
+    io::{self, Read},
+    str,
+};
+
+use crate::{LastSeen, Window};
+
+///
+/// A marker found in the stream, with the number of characters and bytes read up to the end of it
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Marker {
+    pub size: usize,
+    pub position: usize,
+    pub byte_offset: usize,
+}
+
+///
+/// Detector struct, the state of the search after every character fed so far
+///
+pub struct Detector {
+    last_seen: LastSeen,
+    windows: Vec<Window>,
+    position: usize,
+    byte_offset: usize,
+}
+
+impl Detector {
+    ///
+    /// Create a new Detector looking for markers of each of the given sizes
+    ///
+    pub fn new(sizes: &[usize]) -> Detector {
+        Detector {
+            last_seen: LastSeen::new(),
+            windows: sizes.iter().map(|size| Window::new(*size, 0)).collect(),
+            position: 0,
+            byte_offset: 0,
+        }
+    }
+    ///
+    /// The number of characters fed so far
+    ///
+    pub fn position(&self) -> usize {
+        self.position
+    }
+    ///
+    /// Feed the next character of the stream, adding any markers it completes
+    ///
+    pub fn push(&mut self, character: char, markers: &mut Vec<Marker>) {
+        let previous = self.last_seen.insert(character, self.position);
+        self.byte_offset += character.len_utf8();
+        for window in self.windows.iter_mut() {
+            if window.step(self.position, previous) == window.size {
+                let marker = Marker { size: window.size, position: self.position + 1, byte_offset: self.byte_offset };
+                markers.push(marker);
+            }
+        }
+        self.position += 1;
+    }
+}
+
+///
+/// Iterator over every marker in a stream, reading it a chunk at a time
+///
+pub struct Markers<R: Read> {
+    reader: R,
+    detector: Detector,
+    buffer: Vec<u8>,
+    partial: Vec<u8>,
+    held: Vec<char>,
+    pending: VecDeque<Marker>,
+}
+
+impl<R: Read> Markers<R> {
+    ///
+    /// The number of characters read from the stream so far, not counting line terminators that
+    /// may turn out to be at the end
+    ///
+    pub fn position(&self) -> usize {
+        self.detector.position()
+    }
+    ///
+    /// Feed decoded text to the detector, holding back any line terminators at the end of it
+    ///
+    fn feed(&mut self, text: &str) {
+        let mut markers: Vec<Marker> = vec![];
+        for character in text.chars() {
+            if character == '\n' || character == '\r' {
+                self.held.push(character);
+                continue;
+            }
+            for held in self.held.drain(..) {
+                self.detector.push(held, &mut markers);
+            }
+            self.detector.push(character, &mut markers);
+        }
+        self.pending.extend(markers);
+    }
+    ///
+    /// Decode a chunk along with anything left over from the last one, keeping back the start of a
+    /// character that the next chunk finishes
+    ///
+    fn decode(&mut self, read: usize) -> io::Result<()> {
+        let mut bytes = std::mem::take(&mut self.partial);
+        bytes.extend_from_slice(&self.buffer[..read]);
+        let valid = match str::from_utf8(&bytes) {
+            Ok(_) => bytes.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        };
+        self.feed(str::from_utf8(&bytes[..valid]).expect("checked above"));
+        self.partial = bytes.split_off(valid);
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for Markers<R> {
+    type Item = io::Result<Marker>;
+
+    fn next(&mut self) -> Option<io::Result<Marker>> {
+        while self.pending.is_empty() {
+            let read = match self.reader.read(&mut self.buffer) {
+                Ok(0) if self.partial.is_empty() => return None,
+                Ok(0) => {
+                    self.partial.clear();
+                    let error = "stream ends partway through a character";
+                    return Some(Err(io::Error::new(io::ErrorKind::InvalidData, error)));
+                }
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Some(Err(e)),
+            };
+            if let Err(e) = self.decode(read) {
+                return Some(Err(e));
+            }
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+///
+/// Find every marker of the given sizes in a stream
+///
+pub fn markers<R: Read>(reader: R, sizes: &[usize]) -> Markers<R> {
+    Markers {
+        reader,
+        detector: Detector::new(sizes),
+        buffer: vec![0; 64 * 1024],
+        partial: vec![],
+        held: vec![],
+        pending: VecDeque::new(),
+    }
+}
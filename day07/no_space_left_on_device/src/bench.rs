@@ -0,0 +1,94 @@
+///
+/// Benchmark of the cached directory sizes against walking the tree for every directory.
+///
+/// Generates wide and deep transcripts, then times replaying them, part 1 from the cached totals
+/// and from scratch, and working out every total again with recompute_totals. The walk from
+/// scratch is skipped for the largest transcript, where it would take far too long.
+///
+use std::time::Instant;
+
+use super::{shell, FileSystem};
+
+///
+/// Xorshift state deciding how many files each directory gets, how big they are and when the
+/// transcript heads back up
+///
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    ///
+    /// Get the next pseudo random number below the upper bound
+    ///
+    fn below(&mut self, upper: usize) -> usize {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state % upper as u64) as usize
+    }
+}
+
+///
+/// Generate a transcript that creates a given number of directories, no more than max_depth deep.
+///
+/// Each directory is listed once with a few files and then entered, and the walk heads back up
+/// at random, so a large max_depth gives long chains and a small one gives wide trees.
+///
+fn generate_transcript(rng: &mut Rng, dirs: usize, max_depth: usize) -> String {
+    let mut transcript = String::from("$ cd /\n");
+    let mut depth = 0;
+    for dir in 0..dirs {
+        transcript.push_str(&format!("$ ls\ndir d{}\n", dir));
+        for file in 0..rng.below(4) {
+            transcript.push_str(&format!("{} f{}\n", 1 + rng.below(400_000), file));
+        }
+        transcript.push_str(&format!("$ cd d{}\n", dir));
+        depth += 1;
+        while depth == max_depth || (depth > 0 && rng.below(3) == 0) {
+            transcript.push_str("$ cd ..\n");
+            depth -= 1;
+        }
+    }
+    transcript
+}
+
+///
+/// The original part 1, adding up every directory's descendants from scratch
+///
+fn naive_du_max(filesys: &FileSystem, max: u64) -> u64 {
+    let arena = &filesys.filesystem;
+    filesys.root.descendants(arena)
+        .map(|x| x.descendants(arena).map(|y| arena[y].get().get_overall_size()).sum::<u64>())
+        .filter(|x| x <= &max)
+        .sum()
+}
+
+///
+/// Time one step of the benchmark, passing its result through
+///
+fn time<T, F: FnOnce() -> T>(label: &str, f: F) -> T {
+    let start = Instant::now();
+    let result = f();
+    println!("{:<24} {:>10.2?}", label, start.elapsed());
+    result
+}
+
+///
+/// Run the benchmark
+///
+pub fn run() {
+    let mut rng = Rng { state: 0x2022_0007 };
+    for (dirs, max_depth) in [(20_000, 8), (20_000, 2_000), (1_000_000, 8)] {
+        let transcript = generate_transcript(&mut rng, dirs, max_depth);
+        println!("{} directories, at most {} deep, {} byte transcript", dirs, max_depth, transcript.len());
+        let mut filesys = time("interpret", || shell::interpret(&transcript).unwrap());
+        let cached = time("cached du_max", || filesys.du_max(100_000));
+        if dirs <= 20_000 {
+            let naive = time("naive du_max", || naive_du_max(&filesys, 100_000));
+            assert_eq!(naive, cached);
+        }
+        time("post-order pass", || filesys.recompute_totals());
+        assert_eq!(cached, filesys.du_max(100_000));
+    }
+}
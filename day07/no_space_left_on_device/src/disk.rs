@@ -0,0 +1,222 @@
+///
+/// Directory scanner.
+///
+/// Builds a FileSystem from a real directory on disk, so the same questions can be asked of it as
+/// of a transcript. Symbolic links are counted as small files of their own unless they are
+/// followed. On unix each directory and file is only counted once, as `du` does, so links back up
+/// the tree cannot loop and hard links do not add up to more than the space they share.
+///
+/// Sizes are either apparent, the length of each file, or allocated, the space its blocks take up
+/// on disk as reported by `du`. Allocated sizes are only known on unix, elsewhere the length is
+/// used.
+///
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use indextree::NodeId;
+
+use crate::shell::ShellErrorKind;
+use crate::{Directory, File, FileSystem};
+
+///
+/// How the size of a file is measured
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeMode {
+    Apparent,
+    Allocated,
+}
+
+///
+/// A path that could not be read
+///
+#[derive(Debug)]
+pub struct ScanError {
+    pub path: PathBuf,
+    pub error: io::Error,
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.error)
+    }
+}
+
+///
+/// Scanner struct, the options for walking a directory
+///
+pub struct Scanner {
+    pub follow_symlinks: bool,
+    pub skip_hidden: bool,
+    pub sizes: SizeMode,
+}
+
+///
+/// The result of a scan, along with anything inside it that could not be read
+///
+pub struct Scan {
+    pub filesystem: FileSystem,
+    pub skipped: Vec<ScanError>,
+}
+
+impl Scanner {
+    ///
+    /// Walk a directory and everything below it. Only failing to read the directory itself is an
+    /// error, anything unreadable inside it is left out and listed in the result.
+    ///
+    pub fn scan(&self, path: &Path) -> Result<Scan, ScanError> {
+        let error = |error| ScanError { path: path.to_path_buf(), error };
+        let metadata = fs::metadata(path).map_err(error)?;
+        if !metadata.is_dir() {
+            return Err(error(io::Error::new(io::ErrorKind::InvalidInput, "not a directory")));
+        }
+        let mut walk = Walk { filesystem: FileSystem::new(), skipped: vec![], visited: HashSet::new() };
+        walk.visited.extend(identity(&metadata));
+        let root = walk.filesystem.root;
+        self.walk(&mut walk, path, root);
+        if walk.skipped.first().is_some_and(|x| x.path == path) {
+            return Err(walk.skipped.remove(0));
+        }
+        walk.filesystem.recompute_totals();
+        walk.filesystem.cwd = walk.filesystem.root;
+        Ok(Scan { filesystem: walk.filesystem, skipped: walk.skipped })
+    }
+    ///
+    /// Add the contents of a directory on disk to a directory in the FileSystem, then do the same
+    /// for each directory within it. Files are placed straight into their directory, the totals
+    /// are all worked out once the walk is done.
+    ///
+    fn walk(&self, walk: &mut Walk, path: &Path, dir: NodeId) {
+        let entries = match fs::read_dir(path).and_then(|x| x.collect::<io::Result<Vec<fs::DirEntry>>>()) {
+            Ok(entries) => entries,
+            Err(error) => return walk.skipped.push(ScanError { path: path.to_path_buf(), error }),
+        };
+        let mut entries: Vec<(String, PathBuf)> = entries.iter()
+            .map(|x| (entry_name(&x.file_name()), x.path()))
+            .filter(|(name, _)| !(self.skip_hidden && name.starts_with('.')))
+            .collect();
+        entries.sort();
+
+        let mut taken: HashSet<String> = HashSet::new();
+        let mut subdirs: Vec<(NodeId, PathBuf)> = vec![];
+        for (name, entry) in entries {
+            if !taken.insert(name.clone()) {
+                let error = io::Error::new(io::ErrorKind::InvalidData, format!("another entry is also named {:?}", name));
+                walk.skipped.push(ScanError { path: entry, error });
+                continue;
+            }
+            let metadata = match self.metadata(&entry) {
+                Ok(metadata) => metadata,
+                Err(error) => {
+                    walk.skipped.push(ScanError { path: entry, error });
+                    continue;
+                }
+            };
+            if identity(&metadata).is_some_and(|x| !walk.visited.insert(x)) {
+                continue;
+            }
+            let added = match metadata.is_dir() {
+                true => {
+                    walk.filesystem.cwd = dir;
+                    walk.filesystem.mkdir(Directory::new(&name)).map(|_| {
+                        subdirs.extend(walk.filesystem.child(dir, &name).map(|x| (x, entry.clone())));
+                    })
+                }
+                false => walk.filesystem.place(dir, File { name, size: self.size(&metadata) }).map(|_| ()),
+            };
+            if let Err(kind) = added {
+                let message = match kind {
+                    ShellErrorKind::NameConflict(name) => format!("{:?} is both a file and a directory", name),
+                    other => format!("{:?}", other),
+                };
+                walk.skipped.push(ScanError { path: entry, error: io::Error::new(io::ErrorKind::InvalidData, message) });
+            }
+        }
+        for (child, entry) in subdirs {
+            self.walk(walk, &entry, child);
+        }
+    }
+    ///
+    /// The metadata of an entry, looking through symbolic links if they are followed. A link that
+    /// leads nowhere is counted as the link itself.
+    ///
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        let link = fs::symlink_metadata(path)?;
+        if self.follow_symlinks && link.file_type().is_symlink() {
+            return Ok(fs::metadata(path).unwrap_or(link));
+        }
+        Ok(link)
+    }
+    ///
+    /// The size of a file, measured the chosen way
+    ///
+    fn size(&self, metadata: &Metadata) -> u64 {
+        match self.sizes {
+            SizeMode::Apparent => metadata.len(),
+            SizeMode::Allocated => allocated(metadata),
+        }
+    }
+}
+
+///
+/// The state of a walk in progress, the directories and files seen so far by device and inode
+///
+struct Walk {
+    filesystem: FileSystem,
+    skipped: Vec<ScanError>,
+    visited: HashSet<(u64, u64)>,
+}
+
+///
+/// The name of an entry as it appears in the FileSystem. Bytes that are not valid UTF-8 are
+/// written as `\xNN` escapes, so different names on disk stay different.
+///
+#[cfg(unix)]
+fn entry_name(name: &OsStr) -> String {
+    use std::os::unix::ffi::OsStrExt;
+    let mut escaped = String::new();
+    for chunk in name.as_bytes().utf8_chunks() {
+        escaped.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            escaped.push_str(&format!("\\x{:02x}", byte));
+        }
+    }
+    escaped
+}
+
+#[cfg(not(unix))]
+fn entry_name(name: &OsStr) -> String {
+    name.to_string_lossy().into_owned()
+}
+
+///
+/// The device and inode of an entry, which tell whether it has been counted already
+///
+#[cfg(unix)]
+fn identity(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn identity(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+///
+/// The space taken up on disk, in 512 byte blocks whatever the block size of the filesystem
+///
+#[cfg(unix)]
+fn allocated(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn allocated(metadata: &Metadata) -> u64 {
+    metadata.len()
+}
@@ -6,8 +6,40 @@
 /// the OS. The Arena allows the root directory to link to the NodeId of other directories that get
 /// created on the fly, and also track where in the operating system the user (input) currently is.
 ///
-use std::fs;
-use indextree::{Arena, NodeId};
+/// `--tree` lists the whole filesystem, `--du` gives the size of every directory, largest first,
+/// and `--top N` tables the N largest directories. `--depth N` limits each of them to N levels
+/// below the root.
+///
+/// `--scan <dir>` builds the filesystem from a real directory instead of a transcript.
+/// `--follow-symlinks` looks through symbolic links, `--skip-hidden` leaves out names starting
+/// with a dot, and `--allocated` counts the space files take up on disk rather than their length.
+/// `--transcript` writes out a transcript that rebuilds the filesystem.
+///
+/// `--snapshot` writes the filesystem out as nested JSON, or as a flat map from path to size with
+/// `--flat`. `diff <old> <new>` lists what changed between two snapshots or transcripts.
+///
+/// `--disk-size N` and `--needed N` change the size of the disk and the space the update needs
+/// from 70000000 and 30000000. `--plan` chooses any number of directories to delete rather than
+/// just one, deleting as little as possible. It is exact unless `--approximate <epsilon>` is
+/// given, which allows a plan within that fraction of the best one. `--protect <path>`, given any
+/// number of times, keeps a path from being deleted. An exact plan needs a table as large as the
+/// space to free, `--limit N` caps its size (50000000 by default).
+///
+/// `--input <file>` reads a transcript or snapshot other than data.txt. Pass `bench` as the first
+/// argument to time the cached directory sizes against walking the tree for every directory.
+///
+use std::{env, fs, path::Path};
+use indextree::{Arena, NodeEdge, NodeId};
+
+mod bench;
+mod disk;
+mod planner;
+mod report;
+
+mod shell;
+mod snapshot;
+
+use shell::ShellErrorKind;
 
 /// 
 /// FileSystem, used to track the internal state of a filesystem.
@@ -26,53 +58,93 @@ impl FileSystem {
     ///
     fn new() -> Self {
         let mut arena = Arena::new();
-        let current = arena.new_node(Directory { name: "/".to_string(), files: vec![] });
+        let current = arena.new_node(Directory::new("/"));
         FileSystem { filesystem: arena, cwd: current, root: current }
     }
     /// 
+    /// Find a directory by name within another directory.
+    ///
+    fn child(&self, dir: NodeId, name: &str) -> Option<NodeId> {
+        dir.children(&self.filesystem).find(|x| self.filesystem[*x].get().name == name)
+    }
+    /// 
     /// Creates a directory within the current working directory.
     ///
-    fn mkdir(&mut self, new_dir: Directory) {
-        let mut_arena = &mut self.filesystem;
-        self.cwd.append(mut_arena.new_node(new_dir), mut_arena);
+    /// Listing a directory again does nothing, but a name that is already taken by a file is an
+    /// error.
+    ///
+    fn mkdir(&mut self, new_dir: Directory) -> Result<(), ShellErrorKind> {
+        if self.filesystem[self.cwd].get().files.iter().any(|x| x.name == new_dir.name) {
+            return Err(ShellErrorKind::NameConflict(new_dir.name));
+        }
+        if self.child(self.cwd, &new_dir.name).is_none() {
+            let mut_arena = &mut self.filesystem;
+            self.cwd.append(mut_arena.new_node(new_dir), mut_arena);
+        }
+        Ok(())
     }
     /// 
     /// Create a new file in the current working directory.
     ///
-    fn touch(&mut self, new_file: File) {
-        self.filesystem[self.cwd].get_mut().add_file(new_file);
+    /// Listing a file again replaces it, but a name that is already taken by a directory is an
+    /// error. The total size of every directory it is in is kept up to date.
+    ///
+    fn touch(&mut self, new_file: File) -> Result<(), ShellErrorKind> {
+        let size = new_file.size;
+        let replaced = self.place(self.cwd, new_file)?;
+        let mut current = Some(self.cwd);
+        while let Some(id) = current {
+            let node = &mut self.filesystem[id];
+            node.get_mut().total = node.get().total - replaced + size;
+            current = node.parent();
+        }
+        Ok(())
     }
     /// 
-    /// Change into a new directory.
-    ///
-    fn cd(&mut self, next: &str) {
-        let mut_arena = &mut self.filesystem;
-        match next {
-            ".." => self.cwd = mut_arena[self.cwd].parent().unwrap(),
-            oth => self.cwd = self.cwd.children(mut_arena)
-                .find(|x| mut_arena[*x].get().name == oth)
-                .unwrap(),
+    /// Put a file in a directory, leaving the totals alone.
+    ///
+    /// The same checks as touch, for when every file is placed first and the totals are worked
+    /// out afterwards with recompute_totals. Returns the size of the file it replaced, or 0.
+    ///
+    fn place(&mut self, dir: NodeId, new_file: File) -> Result<u64, ShellErrorKind> {
+        if self.child(dir, &new_file.name).is_some() {
+            return Err(ShellErrorKind::NameConflict(new_file.name));
         }
+        Ok(self.filesystem[dir].get_mut().add_file(new_file))
     }
     /// 
-    /// Get the total size of a directory.
+    /// Change directory.
+    ///
+    /// Paths can have any number of `/` separated parts, and start from the root when they begin
+    /// with `/`. Going up from the root stays at the root.
+    ///
+    fn cd(&mut self, path: &str) -> Result<(), ShellErrorKind> {
+        let mut cwd = if path.starts_with('/') { self.root } else { self.cwd };
+        for part in path.split('/').filter(|x| !x.is_empty() && *x != ".") {
+            cwd = match part {
+                ".." => self.filesystem[cwd].parent().unwrap_or(cwd),
+                name => self.child(cwd, name).ok_or_else(|| ShellErrorKind::UnknownDirectory(path.to_string()))?,
+            };
+        }
+        self.cwd = cwd;
+        Ok(())
+    }
+    /// 
+    /// Get the total size of the current working directory.
     ///
     /// This includes both files with direct ownership, and files of indirect ownership.
     ///
-    fn du(&mut self) -> u64 {
-        let mut_arena = &mut self.filesystem;
-        self.cwd.descendants(mut_arena).map(|x| mut_arena[x].get().get_overall_size()).sum()
+    fn du(&self) -> u64 {
+        self.filesystem[self.cwd].get().total
     }
     /// 
     /// Get the cumulative sum of all directories with a max size cutoff.
     ///
     /// This includes both files with direct ownership, and files of indirect ownership.
     ///
-    fn du_max(&mut self, max: u64) -> u64 {
-        let mut_arena = &mut self.filesystem;
-        self.root.descendants(mut_arena)
-            .into_iter()
-            .map(|x| x.descendants(mut_arena).map(|y| mut_arena[y].get().get_overall_size()).sum::<u64>())
+    fn du_max(&self, max: u64) -> u64 {
+        self.root.descendants(&self.filesystem)
+            .map(|x| self.filesystem[x].get().total)
             .filter(|x| x <= &max)
             .sum()
     }
@@ -80,37 +152,55 @@ impl FileSystem {
     /// Given a target for the required space in the operating system, find the smallest dir
     /// possible to delete to create the required space.
     ///
-    fn find_smallest_deletable_dir(&mut self, target: u64) -> u64 {
-        let mut_arena = &mut self.filesystem;
-        self.root.descendants(mut_arena)
-            .into_iter()
-            .map(|x| {
-                 x.descendants(mut_arena)
-                    .map(|y| mut_arena[y].get().get_overall_size())
-                    .sum::<u64>()
-            })
+    /// There is none when the target is more than the whole filesystem.
+    ///
+    fn find_smallest_deletable_dir(&self, target: u64) -> Option<u64> {
+        self.root.descendants(&self.filesystem)
+            .map(|x| self.filesystem[x].get().total)
             .filter(|x| x >= &target)
             .min()
-            .unwrap()
     }
+    /// 
+    /// Work out the total size of every directory from scratch.
     ///
-    /// Change dir to the root dir
+    /// A post-order pass, so each directory is visited after all of its children and only has to
+    /// add up their totals. Scanning a directory on disk fills in every file first and then uses
+    /// this, rather than updating every parent as each file is added.
     ///
-    fn to_root_dir(&mut self) {
-        self.cwd = self.root;
+    fn recompute_totals(&mut self) {
+        let order: Vec<NodeId> = self.root.traverse(&self.filesystem)
+            .filter_map(|x| match x {
+                NodeEdge::End(id) => Some(id),
+                NodeEdge::Start(_) => None,
+            })
+            .collect();
+        for id in order {
+            let children: u64 = id.children(&self.filesystem).map(|x| self.filesystem[x].get().total).sum();
+            let dir = self.filesystem[id].get_mut();
+            dir.total = dir.get_overall_size() + children;
+        }
     }
 }
 
 /// 
 /// Directory, used to store the contents of a directory.
 ///
+/// The total is the size of every file within it, however deeply nested.
+///
 #[derive(Debug)]
 struct Directory {
     name: String,
     files: Vec<File>,
+    total: u64,
 }
 
 impl Directory {
+    /// 
+    /// Create a new, empty directory.
+    ///
+    fn new(name: &str) -> Directory {
+        Directory { name: name.to_string(), files: vec![], total: 0 }
+    }
     /// 
     /// Retrieves the overall size of this directory (files only).
     ///
@@ -120,10 +210,17 @@ impl Directory {
     /// 
     /// Add a file to the directory.
     ///
-    /// Appends a new file to the internal vector
+    /// Appends a new file to the internal vector, replacing any file with the same name. Returns
+    /// the size of the replaced file, or 0 for a new one.
     ///
-    fn add_file(&mut self, file: File) {
-        self.files.push(file);
+    fn add_file(&mut self, file: File) -> u64 {
+        match self.files.iter_mut().find(|x| x.name == file.name) {
+            Some(existing) => std::mem::replace(existing, file).size,
+            None => {
+                self.files.push(file);
+                0
+            }
+        }
     }
 }
 
@@ -132,51 +229,159 @@ impl Directory {
 ///
 #[derive(Debug)]
 struct File {
-    _name: String,
+    name: String,
     size: u64,
 }
 
 /// 
-/// Check if the input line is a command. Commands are prefixed with a $ char.
+/// Get the value following an option on the command line, if the option was given
 ///
-fn is_command(line: &str) -> bool {
-    line.chars().rev().last().unwrap_or(' ') == '$'
+fn option_value<'a>(args: &'a [String], name: &str) -> Result<Option<&'a str>, String> {
+    match args.iter().position(|x| x == name) {
+        Some(i) => match args.get(i + 1) {
+            Some(value) => Ok(Some(value.as_str())),
+            None => Err(format!("{} needs a value", name)),
+        },
+        None => Ok(None),
+    }
 }
-
+/// 
+/// Get the value following every use of an option on the command line
+///
+fn option_values(args: &[String], name: &str) -> Result<Vec<String>, String> {
+    let mut values = vec![];
+    for (i, _) in args.iter().enumerate().filter(|(_, x)| *x == name) {
+        match args.get(i + 1) {
+            Some(value) => values.push(value.clone()),
+            None => return Err(format!("{} needs a value", name)),
+        }
+    }
+    Ok(values)
+}
+/// 
+/// Get a number following an option on the command line, if the option was given
+///
+fn option_number(args: &[String], name: &str) -> Result<Option<usize>, String> {
+    match option_value(args, name)? {
+        Some(value) => value.parse::<usize>().map(Some).map_err(|_| format!("{} needs a number, not {:?}", name, value)),
+        None => Ok(None),
+    }
+}
+/// 
+/// Entrypoint
+///
 fn main() {
-    let data = fs::read_to_string("data.txt").unwrap();
-    let mut data_iter = data.lines();
-    let mut filesys = FileSystem::new();
-
-    // we aren't interested in the first line..
-    data_iter.next();
-    for line in data_iter {
-        if is_command(line) {
-            let mut command = line.strip_prefix("$ ").unwrap().split(" ");
-            match command.next().unwrap() {
-                "cd" => filesys.cd(command.next().unwrap()),
-                _ => (),
-            }
-        } else {
-            let mut output = line.split(" ");
-            match output.next().unwrap() {
-                "dir" => filesys.mkdir( 
-                    Directory { name: output.next().unwrap().to_string(), files: vec![] } 
-                ),
-                x => filesys.touch( 
-                    File { _name: output.next().unwrap().to_string(), size: x.parse::<u64>().unwrap() } 
-                ),
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("bench") {
+        bench::run();
+        return;
+    }
+    let result = match args.first().map(String::as_str) {
+        Some("diff") => compare(&args[1..]),
+        _ => run_with_args(&args),
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+    }
+}
+/// 
+/// Read a filesystem from a file, either a JSON snapshot or a transcript
+///
+fn load(input: &str) -> Result<FileSystem, String> {
+    let data = fs::read_to_string(input).map_err(|e| format!("{}: {}", input, e))?;
+    match data.trim_start().starts_with('{') {
+        true => snapshot::load(&data).map_err(|e| format!("{}: {}", input, e)),
+        false => shell::interpret(&data).map_err(|e| format!("{}: {}", input, e)),
+    }
+}
+/// 
+/// Compare two filesystems, each either a snapshot or a transcript
+///
+fn compare(args: &[String]) -> Result<(), String> {
+    let [old, new] = args else {
+        return Err("diff needs an old and a new file".to_string());
+    };
+    println!("{}", snapshot::diff(&load(old)?, &load(new)?));
+    Ok(())
+}
+/// 
+/// Carry out whatever the command line asks for
+///
+fn run_with_args(args: &[String]) -> Result<(), String> {
+    let mut filesys = match option_value(args, "--scan")? {
+        Some(path) => {
+            let scanner = disk::Scanner {
+                follow_symlinks: args.iter().any(|x| x == "--follow-symlinks"),
+                skip_hidden: args.iter().any(|x| x == "--skip-hidden"),
+                sizes: match args.iter().any(|x| x == "--allocated") {
+                    true => disk::SizeMode::Allocated,
+                    false => disk::SizeMode::Apparent,
+                },
+            };
+            let scan = scanner.scan(Path::new(path)).map_err(|e| e.to_string())?;
+            for skipped in &scan.skipped {
+                eprintln!("Skipped {}", skipped);
             }
+            scan.filesystem
         }
+        None => load(option_value(args, "--input")?.unwrap_or("data.txt"))?,
+    };
+
+    if args.iter().any(|x| x == "--snapshot") {
+        let snapshot = match args.iter().any(|x| x == "--flat") {
+            true => snapshot::flat(&filesys),
+            false => snapshot::nested(&filesys),
+        };
+        println!("{}", serde_json::to_string_pretty(&snapshot).map_err(|e| e.to_string())?);
+        return Ok(());
+    }
+    if args.iter().any(|x| x == "--transcript") {
+        println!("{}", shell::transcript(&filesys)?);
+        return Ok(());
+    }
+
+    let depth = option_number(args, "--depth")?;
+    if args.iter().any(|x| x == "--tree") {
+        println!("{}", report::tree(&filesys, depth));
+        return Ok(());
+    }
+    if args.iter().any(|x| x == "--du") {
+        println!("{}", report::du(&filesys, depth));
+        return Ok(());
+    }
+    if let Some(count) = option_number(args, "--top")? {
+        println!("{}", report::top(&filesys, count, depth));
+        return Ok(());
+    }
+
+    filesys.cwd = filesys.root;
+    let overall_used = filesys.du();
+    let overall_available = option_number(args, "--disk-size")?.map_or(70000000, |x| x as u64);
+    let needed = option_number(args, "--needed")?.map_or(30000000, |x| x as u64);
+    let target = (overall_used + needed).saturating_sub(overall_available);
+    if args.iter().any(|x| x == "--plan") {
+        let planner = planner::Planner {
+            mode: match option_value(args, "--approximate")? {
+                Some(epsilon) => match epsilon.parse::<f64>() {
+                    Ok(epsilon) if epsilon > 0.0 => planner::Mode::Approximate(epsilon),
+                    _ => return Err(format!("--approximate needs a positive number, not {:?}", epsilon)),
+                },
+                None => planner::Mode::Exact,
+            },
+            protected: option_values(args, "--protect")?,
+            limit: option_number(args, "--limit")?.unwrap_or(50_000_000),
+        };
+        println!("{}", planner.plan(&filesys, target).map_err(|e| e.to_string())?);
+        return Ok(());
     }
 
     // part 1
     println!("{}", filesys.du_max(100000));
 
     // part 2
-    filesys.to_root_dir();
-    let overall_used = filesys.du();
-    let overall_available = 70000000;
-    let needed = 30000000;
-    println!("{}", filesys.find_smallest_deletable_dir(overall_used + needed - overall_available));
+    match filesys.find_smallest_deletable_dir(target) {
+        Some(size) => println!("{}", size),
+        None => return Err(format!("no directory is large enough to free {} bytes", target)),
+    }
+    Ok(())
 }
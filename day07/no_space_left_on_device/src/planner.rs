@@ -0,0 +1,373 @@
+///
+/// Deletion planner.
+///
+/// Chooses a set of directories to delete that frees at least a target number of bytes, deleting
+/// as little as possible. No chosen directory is inside another, as deleting the outer one would
+/// already take the inner one with it.
+///
+/// The directories are visited in pre-order, carrying every total that can be freed by the
+/// choices made so far. Skipping a directory moves on to the next one, deleting it jumps past
+/// everything inside it. Only totals short of the target are carried, the smallest one that
+/// reaches it is kept aside as the best so far.
+///
+/// The exact mode keeps a table with an entry for every total short of the target, so it gives up
+/// when the target is more than a limit. The approximate mode drops any total within
+/// epsilon * target / directories below a larger one it keeps. The plan can end up deleting at
+/// most epsilon * target more than the best one, and as the best one deletes at least the target,
+/// that is no more than (1 + epsilon) times as much.
+///
+/// Protected paths are never deleted. Protecting a directory also protects everything in it, and
+/// no directory that holds a protected path can be deleted either.
+///
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use indextree::NodeId;
+
+use crate::report::path;
+use crate::FileSystem;
+
+///
+/// How thoroughly to search
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Exact,
+    Approximate(f64),
+}
+
+///
+/// The reasons there is no plan
+///
+#[derive(Debug, PartialEq, Eq)]
+pub enum PlanError {
+    UnknownPath(String),
+    NotEnough { target: u64, available: u64 },
+    TargetTooLarge { target: u64, limit: usize },
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlanError::UnknownPath(path) => write!(f, "{:?} is not in the filesystem", path),
+            PlanError::NotEnough { target, available } => {
+                write!(f, "{} bytes are needed but only {} can be deleted", target, available)
+            }
+            PlanError::TargetTooLarge { target, limit } => {
+                write!(f, "an exact plan for {} bytes needs a table larger than {}, try an approximate plan", target, limit)
+            }
+        }
+    }
+}
+
+///
+/// A plan, with what it takes to explain it
+///
+#[derive(Debug)]
+pub struct Plan {
+    pub mode: Mode,
+    pub target: u64,
+    pub dirs: Vec<(String, u64)>,
+    pub freed: u64,
+    pub best_single: Option<(String, u64)>,
+    pub protected: usize,
+    pub totals: usize,
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mode = match self.mode {
+            Mode::Exact => "exact".to_string(),
+            Mode::Approximate(epsilon) => format!("approximate, within {}% of the best", epsilon * 100.0),
+        };
+        writeln!(f, "plan to free at least {} bytes ({}):", self.target, mode)?;
+        for (path, size) in &self.dirs {
+            writeln!(f, "  delete {:<40} {:>12}", path, size)?;
+        }
+        let surplus = self.freed - self.target;
+        write!(f, "frees {} bytes, {} more than needed", self.freed, surplus)?;
+        match &self.best_single {
+            Some((path, size)) if *size > self.freed => {
+                let extra = size - self.freed;
+                write!(f, "\nthe best single directory, {}, would free {} bytes, {} more than this", path, size, extra)?
+            }
+            Some((path, _)) => write!(f, "\nno better than deleting {} on its own", path)?,
+            None => write!(f, "\nno single directory frees enough on its own")?,
+        }
+        if self.protected > 0 {
+            write!(f, "\n{} directories could not be deleted to keep protected paths", self.protected)?;
+        }
+        write!(f, "\n{} totals were considered", self.totals)
+    }
+}
+
+///
+/// Planner struct, the search options and the paths that must not be deleted
+///
+pub struct Planner {
+    pub mode: Mode,
+    pub protected: Vec<String>,
+    pub limit: usize,
+}
+
+///
+/// The directories chosen so far, as a list that shares its start with the lists it grew from
+///
+enum Picks {
+    Nil,
+    Cons(usize, Rc<Picks>),
+}
+
+///
+/// The bytes a plan frees, the positions of the directories it deletes, and how many totals were
+/// carried to find it
+///
+type Search = (u64, Vec<usize>, usize);
+
+///
+/// A total that can be freed, and the directories that free it
+///
+#[derive(Clone)]
+struct Entry {
+    sum: u64,
+    picks: Rc<Picks>,
+}
+
+impl Planner {
+    ///
+    /// Plan how to free at least the target number of bytes
+    ///
+    pub fn plan(&self, filesys: &FileSystem, target: u64) -> Result<Plan, PlanError> {
+        let order: Vec<NodeId> = filesys.root.descendants(&filesys.filesystem).collect();
+        let sizes: Vec<u64> = order.iter().map(|x| filesys.filesystem[*x].get().total).collect();
+        let paths: Vec<String> = order.iter().map(|x| path(filesys, *x)).collect();
+        let index: HashMap<NodeId, usize> = order.iter().enumerate().map(|(i, x)| (*x, i)).collect();
+        let ends = subtree_ends(filesys, &order, &index);
+        let blocked = self.blocked(filesys, &order, &index, &paths, &ends)?;
+
+        let mut available = 0;
+        let mut i = 0;
+        while i < order.len() {
+            match blocked[i] {
+                true => i += 1,
+                false => {
+                    available += sizes[i];
+                    i = ends[i];
+                }
+            }
+        }
+        if available < target {
+            return Err(PlanError::NotEnough { target, available });
+        }
+        let best_single = (0..order.len())
+            .filter(|x| !blocked[*x] && sizes[*x] >= target)
+            .min_by_key(|x| sizes[*x])
+            .map(|x| (paths[x].clone(), sizes[x]));
+
+        let (freed, mut chosen, totals) = match self.mode {
+            _ if target == 0 => (0, vec![], 0),
+            Mode::Exact => self.exact(&sizes, &ends, &blocked, target)?,
+            Mode::Approximate(epsilon) => approximate(&sizes, &ends, &blocked, target, epsilon),
+        };
+        chosen.sort_by(|x, y| sizes[*y].cmp(&sizes[*x]).then(x.cmp(y)));
+        Ok(Plan {
+            mode: self.mode,
+            target,
+            dirs: chosen.iter().map(|x| (paths[*x].clone(), sizes[*x])).collect(),
+            freed,
+            best_single,
+            protected: blocked.iter().filter(|x| **x).count(),
+            totals,
+        })
+    }
+    ///
+    /// Find the best plan. Skipping a directory is always allowed, so a total that can be freed by
+    /// the time one directory is reached can still be freed at every later one. That means a single
+    /// table is enough, holding for every total short of the target the earliest position it can
+    /// be freed by and the directory deleted last to free it. Each total is carried on from the
+    /// position it is first freed by, so the work done is the number of totals carried.
+    ///
+    fn exact(&self, sizes: &[u64], ends: &[usize], blocked: &[bool], target: u64) -> Result<Search, PlanError> {
+        if target > self.limit as u64 || target > u32::MAX as u64 {
+            return Err(PlanError::TargetTooLarge { target, limit: self.limit });
+        }
+        let mut earliest: Vec<u32> = vec![u32::MAX; target as usize];
+        let mut last: Vec<u32> = vec![0; target as usize];
+        let mut freed_by: Vec<Vec<u32>> = vec![vec![]; sizes.len() + 1];
+        let mut carried: Vec<u32> = vec![];
+        let mut best: Option<(u64, usize, u32)> = None;
+        earliest[0] = 0;
+        freed_by[0].push(0);
+        let mut totals = 0;
+        for i in 0..sizes.len() {
+            if best.is_some_and(|x| x.0 == target) {
+                break;
+            }
+            // A total can be freed by more than one position, only the earliest counts
+            for sum in std::mem::take(&mut freed_by[i]) {
+                if earliest[sum as usize] == i as u32 {
+                    carried.push(sum);
+                }
+            }
+            totals += carried.len();
+            if blocked[i] {
+                continue;
+            }
+            for &sum in &carried {
+                let freed = sum as u64 + sizes[i];
+                if freed >= target {
+                    if best.is_none_or(|x| freed < x.0) {
+                        best = Some((freed, i, sum));
+                    }
+                } else if (ends[i] as u32) < earliest[freed as usize] {
+                    earliest[freed as usize] = ends[i] as u32;
+                    last[freed as usize] = i as u32;
+                    freed_by[ends[i]].push(freed as u32);
+                }
+            }
+        }
+
+        let (freed, i, mut sum) = best.expect("deleting every directory that is not blocked frees enough");
+        let mut chosen = vec![i];
+        while sum > 0 {
+            let dir = last[sum as usize] as usize;
+            chosen.push(dir);
+            sum -= sizes[dir] as u32;
+        }
+        Ok((freed, chosen, totals))
+    }
+    ///
+    /// Which directories cannot be deleted without deleting a protected path
+    ///
+    fn blocked(
+        &self,
+        filesys: &FileSystem,
+        order: &[NodeId],
+        index: &HashMap<NodeId, usize>,
+        paths: &[String],
+        ends: &[usize],
+    ) -> Result<Vec<bool>, PlanError> {
+        let mut blocked = vec![false; order.len()];
+        for protected in &self.protected {
+            let wanted = match protected.trim_end_matches('/') {
+                "" => "/",
+                trimmed => trimmed,
+            };
+            let (position, is_dir) = match paths.iter().position(|x| x == wanted) {
+                Some(position) => (position, true),
+                None => {
+                    let (parent, name) = wanted.rsplit_once('/').ok_or_else(|| PlanError::UnknownPath(protected.clone()))?;
+                    let parent = if parent.is_empty() { "/" } else { parent };
+                    let position = paths.iter().position(|x| x == parent)
+                        .filter(|x| filesys.filesystem[order[*x]].get().files.iter().any(|x| x.name == name))
+                        .ok_or_else(|| PlanError::UnknownPath(protected.clone()))?;
+                    (position, false)
+                }
+            };
+            if is_dir {
+                blocked[position..ends[position]].fill(true);
+            }
+            for ancestor in order[position].ancestors(&filesys.filesystem) {
+                blocked[index[&ancestor]] = true;
+            }
+        }
+        Ok(blocked)
+    }
+}
+
+///
+/// Where the pre-order run of each directory and everything in it ends
+///
+fn subtree_ends(filesys: &FileSystem, order: &[NodeId], index: &HashMap<NodeId, usize>) -> Vec<usize> {
+    let mut lengths = vec![1; order.len()];
+    for i in (0..order.len()).rev() {
+        if let Some(parent) = filesys.filesystem[order[i]].parent() {
+            lengths[index[&parent]] += lengths[i];
+        }
+    }
+    lengths.iter().enumerate().map(|(i, x)| i + x).collect()
+}
+
+///
+/// Find a plan that deletes at most (1 + epsilon) times as much as the best one, carrying sorted
+/// lists of totals from each position to the next, trimmed to keep them short
+///
+fn approximate(sizes: &[u64], ends: &[usize], blocked: &[bool], target: u64, epsilon: f64) -> Search {
+    let mut pending: Vec<Vec<Entry>> = vec![vec![]; sizes.len() + 1];
+    pending[0].push(Entry { sum: 0, picks: Rc::new(Picks::Nil) });
+    let gap = (epsilon * target as f64 / sizes.len() as f64) as u64;
+    let mut best: Option<Entry> = None;
+    let mut totals = 0;
+    for i in 0..sizes.len() {
+        if best.as_ref().is_some_and(|x| x.sum == target) {
+            break;
+        }
+        let current = trim(std::mem::take(&mut pending[i]), gap);
+        totals += current.len();
+        if !blocked[i] {
+            let mut deleted: Vec<Entry> = vec![];
+            for entry in &current {
+                let picks = Rc::new(Picks::Cons(i, entry.picks.clone()));
+                let freed = Entry { sum: entry.sum + sizes[i], picks };
+                match freed.sum >= target {
+                    true if best.as_ref().is_none_or(|x| freed.sum < x.sum) => best = Some(freed),
+                    true => {}
+                    false => deleted.push(freed),
+                }
+            }
+            let after = std::mem::take(&mut pending[ends[i]]);
+            pending[ends[i]] = merge(after, deleted);
+        }
+        let next = std::mem::take(&mut pending[i + 1]);
+        pending[i + 1] = merge(next, current);
+    }
+
+    let best = best.expect("deleting every directory that is not blocked frees enough");
+    let mut chosen: Vec<usize> = vec![];
+    let mut picks = &best.picks;
+    while let Picks::Cons(i, rest) = picks.as_ref() {
+        chosen.push(*i);
+        picks = rest;
+    }
+    (best.sum, chosen, totals)
+}
+
+///
+/// Merge two lists sorted by total, keeping one entry for each total
+///
+fn merge(a: Vec<Entry>, b: Vec<Entry>) -> Vec<Entry> {
+    let mut merged: Vec<Entry> = Vec::with_capacity(a.len() + b.len());
+    let (mut a, mut b) = (a.into_iter().peekable(), b.into_iter().peekable());
+    loop {
+        let next = match (a.peek(), b.peek()) {
+            (Some(x), Some(y)) if x.sum <= y.sum => a.next(),
+            (Some(_), Some(_)) => b.next(),
+            (Some(_), None) => a.next(),
+            (None, _) => b.next(),
+        };
+        match next {
+            Some(entry) if merged.last().is_some_and(|x| x.sum == entry.sum) => {}
+            Some(entry) => merged.push(entry),
+            None => return merged,
+        }
+    }
+}
+
+///
+/// Drop every total that is within the gap below a larger one that is kept. The larger one frees
+/// at least as much, and deletes at most the gap more.
+///
+fn trim(entries: Vec<Entry>, gap: u64) -> Vec<Entry> {
+    if gap == 0 {
+        return entries;
+    }
+    let mut kept: Vec<Entry> = vec![];
+    for entry in entries.into_iter().rev() {
+        if kept.last().is_none_or(|x| entry.sum + gap < x.sum) {
+            kept.push(entry);
+        }
+    }
+    kept.reverse();
+    kept
+}
@@ -0,0 +1,114 @@
+///
+/// Reports on a FileSystem.
+///
+/// `tree` lists every directory and file in the same format as the puzzle, `du` gives the total
+/// size of each directory, largest first, and `top` tables the largest directories. Each can be
+/// limited to a number of levels below the root.
+///
+use indextree::NodeId;
+
+use crate::FileSystem;
+
+///
+/// Format a size with binary units, in the style of `du -h`
+///
+pub fn human(size: u64) -> String {
+    let mut value = size as f64;
+    for unit in ["B", "K", "M", "G", "T"] {
+        if value < 1024.0 || unit == "T" {
+            return match unit {
+                "B" => format!("{}B", size),
+                _ if value < 10.0 => format!("{:.1}{}", value, unit),
+                _ => format!("{:.0}{}", value, unit),
+            };
+        }
+        value /= 1024.0;
+    }
+    unreachable!()
+}
+
+///
+/// The full path of a directory
+///
+pub fn path(filesys: &FileSystem, dir: NodeId) -> String {
+    let mut names: Vec<&str> = dir.ancestors(&filesys.filesystem)
+        .filter(|x| *x != filesys.root)
+        .map(|x| filesys.filesystem[x].get().name.as_str())
+        .collect();
+    names.reverse();
+    format!("/{}", names.join("/"))
+}
+
+///
+/// Every directory no more than max_depth levels below the root, along with its depth
+///
+fn directories(filesys: &FileSystem, max_depth: Option<usize>) -> Vec<(NodeId, usize)> {
+    let mut dirs: Vec<(NodeId, usize)> = vec![];
+    let mut stack: Vec<(NodeId, usize)> = vec![(filesys.root, 0)];
+    while let Some((dir, depth)) = stack.pop() {
+        dirs.push((dir, depth));
+        if max_depth.is_none_or(|x| depth < x) {
+            stack.extend(dir.children(&filesys.filesystem).map(|x| (x, depth + 1)));
+        }
+    }
+    dirs
+}
+
+///
+/// List a directory and everything in it, sorted by name, in the puzzle's format
+///
+fn tree_entry(filesys: &FileSystem, dir: NodeId, depth: usize, max_depth: Option<usize>, lines: &mut Vec<String>) {
+    let directory = filesys.filesystem[dir].get();
+    lines.push(format!("{}- {} (dir)", "  ".repeat(depth), directory.name));
+    if max_depth.is_some_and(|x| depth >= x) {
+        return;
+    }
+    let mut entries: Vec<(&str, Option<NodeId>, u64)> = dir.children(&filesys.filesystem)
+        .map(|x| (filesys.filesystem[x].get().name.as_str(), Some(x), 0))
+        .chain(directory.files.iter().map(|x| (x.name.as_str(), None, x.size)))
+        .collect();
+    entries.sort_by_key(|x| x.0);
+    for (name, child, size) in entries {
+        match child {
+            Some(child) => tree_entry(filesys, child, depth + 1, max_depth, lines),
+            None => lines.push(format!("{}- {} (file, size={})", "  ".repeat(depth + 1), name, size)),
+        }
+    }
+}
+
+///
+/// An indented listing of every directory and file, as in the puzzle
+///
+pub fn tree(filesys: &FileSystem, max_depth: Option<usize>) -> String {
+    let mut lines: Vec<String> = vec![];
+    tree_entry(filesys, filesys.root, 0, max_depth, &mut lines);
+    lines.join("\n")
+}
+
+///
+/// The total size and path of each directory, largest first, in the style of `du -h | sort -rh`
+///
+pub fn du(filesys: &FileSystem, max_depth: Option<usize>) -> String {
+    let mut dirs: Vec<(u64, String)> = directories(filesys, max_depth)
+        .into_iter()
+        .map(|(x, _)| (filesys.filesystem[x].get().total, path(filesys, x)))
+        .collect();
+    dirs.sort_by(|x, y| y.0.cmp(&x.0).then_with(|| x.1.cmp(&y.1)));
+    dirs.iter().map(|(size, path)| format!("{}\t{}", human(*size), path)).collect::<Vec<String>>().join("\n")
+}
+
+///
+/// A table of the largest directories
+///
+pub fn top(filesys: &FileSystem, count: usize, max_depth: Option<usize>) -> String {
+    let mut dirs: Vec<(u64, usize, String)> = directories(filesys, max_depth)
+        .into_iter()
+        .map(|(x, depth)| (filesys.filesystem[x].get().total, depth, path(filesys, x)))
+        .collect();
+    dirs.sort_by(|x, y| y.0.cmp(&x.0).then_with(|| x.2.cmp(&y.2)));
+    let mut lines = vec![format!("{:>4}  {:>7}  {:>12}  {:>5}  {}", "rank", "size", "bytes", "depth", "path")];
+    for (rank, (size, depth, path)) in dirs.iter().take(count).enumerate() {
+        lines.push(format!("{:>4}  {:>7}  {:>12}  {:>5}  {}", rank + 1, human(*size), size, depth, path));
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,130 @@
+///
+/// Shell transcript interpreter.
+///
+/// Replays a transcript of `cd` and `ls` commands and their output to build up a FileSystem.
+/// `cd` takes any path, relative or absolute, and a directory can be listed any number of times
+/// without its contents being counted twice. A FileSystem can also be written back out as a
+/// transcript.
+///
+use std::fmt;
+
+use indextree::NodeId;
+
+use crate::{Directory, File, FileSystem};
+
+///
+/// The reasons a transcript cannot be replayed
+///
+#[derive(Debug, PartialEq, Eq)]
+pub enum ShellErrorKind {
+    UnknownCommand(String),
+    MalformedCommand,
+    UnknownDirectory(String),
+    OutputWithoutLs,
+    MalformedOutput,
+    NameConflict(String),
+}
+
+///
+/// An error in a transcript, with the line it was found on
+///
+#[derive(Debug, PartialEq, Eq)]
+pub struct ShellError {
+    pub line: usize,
+    pub text: String,
+    pub kind: ShellErrorKind,
+}
+
+impl fmt::Display for ShellError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {} ({:?}): ", self.line, self.text)?;
+        match &self.kind {
+            ShellErrorKind::UnknownCommand(command) => write!(f, "unknown command {:?}", command),
+            ShellErrorKind::MalformedCommand => write!(f, "expected `$ cd <path>` or `$ ls`"),
+            ShellErrorKind::UnknownDirectory(path) => write!(f, "no such directory {:?}", path),
+            ShellErrorKind::OutputWithoutLs => write!(f, "output without a preceding `$ ls`"),
+            ShellErrorKind::MalformedOutput => write!(f, "expected `dir <name>` or `<size> <name>`"),
+            ShellErrorKind::NameConflict(name) => write!(f, "{:?} is both a file and a directory", name),
+        }
+    }
+}
+
+///
+/// Run a single line of the transcript. Listing tracks whether the last command was `ls`.
+///
+fn step(filesys: &mut FileSystem, line: &str, listing: &mut bool) -> Result<(), ShellErrorKind> {
+    if let Some(command) = line.strip_prefix('$') {
+        let command: Vec<&str> = command.split_whitespace().collect();
+        *listing = false;
+        return match command[..] {
+            ["cd", path] => filesys.cd(path),
+            ["ls"] => {
+                *listing = true;
+                Ok(())
+            }
+            [name, ..] if name != "cd" && name != "ls" => Err(ShellErrorKind::UnknownCommand(name.to_string())),
+            _ => Err(ShellErrorKind::MalformedCommand),
+        };
+    }
+    if !*listing {
+        return Err(ShellErrorKind::OutputWithoutLs);
+    }
+    match line.split_whitespace().collect::<Vec<&str>>()[..] {
+        ["dir", name] => filesys.mkdir(Directory::new(name)),
+        [size, name] => match size.parse::<u64>() {
+            Ok(size) => filesys.touch(File { name: name.to_string(), size }),
+            Err(_) => Err(ShellErrorKind::MalformedOutput),
+        },
+        _ => Err(ShellErrorKind::MalformedOutput),
+    }
+}
+
+///
+/// Replay a whole transcript, starting in the root directory. Blank lines are skipped.
+///
+pub fn interpret(transcript: &str) -> Result<FileSystem, ShellError> {
+    let mut filesys = FileSystem::new();
+    let mut listing = false;
+    for (i, line) in transcript.lines().enumerate().filter(|(_, x)| !x.trim().is_empty()) {
+        step(&mut filesys, line, &mut listing)
+            .map_err(|kind| ShellError { line: i + 1, text: line.to_string(), kind })?;
+    }
+    Ok(filesys)
+}
+
+///
+/// Write a transcript that rebuilds the FileSystem when replayed, listing each directory in turn
+/// from the root down. Names with whitespace in them cannot be written, as the output of `ls`
+/// would not read back.
+///
+pub fn transcript(filesys: &FileSystem) -> Result<String, String> {
+    let mut lines: Vec<String> = vec!["$ cd /".to_string()];
+    list(filesys, filesys.root, &mut lines)?;
+    Ok(lines.join("\n"))
+}
+
+///
+/// Write the `ls` of a directory, then `cd` into each directory within it and back out again
+///
+fn list(filesys: &FileSystem, dir: NodeId, lines: &mut Vec<String>) -> Result<(), String> {
+    let check = |name: &str| match name.is_empty() || name.contains(char::is_whitespace) {
+        true => Err(format!("{:?} cannot be written to a transcript", name)),
+        false => Ok(()),
+    };
+    lines.push("$ ls".to_string());
+    for child in dir.children(&filesys.filesystem) {
+        let name = &filesys.filesystem[child].get().name;
+        check(name)?;
+        lines.push(format!("dir {}", name));
+    }
+    for file in &filesys.filesystem[dir].get().files {
+        check(&file.name)?;
+        lines.push(format!("{} {}", file.size, file.name));
+    }
+    for child in dir.children(&filesys.filesystem) {
+        lines.push(format!("$ cd {}", filesys.filesystem[child].get().name));
+        list(filesys, child, lines)?;
+        lines.push("$ cd ..".to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,316 @@
+///
+/// Filesystem snapshots.
+///
+/// A FileSystem can be saved as JSON in one of two shapes. Nested mirrors the tree, each
+/// directory holding its files by name and a list of the directories within it. Flat is a single
+/// object from path to size, where a path ending in `/` is a directory and its size is the total
+/// of everything in it. Directory totals are only there to read, loading works them out again.
+///
+/// Two filesystems can be compared, listing what was added, removed or resized along with how
+/// much the total of each directory changed by.
+///
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use indextree::NodeId;
+use serde::{Deserialize, Serialize};
+
+use crate::report::path;
+use crate::shell::ShellErrorKind;
+use crate::{Directory, File, FileSystem};
+
+///
+/// A directory and everything in it, for the nested shape
+///
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Node {
+    pub name: String,
+    #[serde(default)]
+    pub total: u64,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub files: BTreeMap<String, u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dirs: Vec<Node>,
+}
+
+///
+/// A snapshot in either shape. Paths in the flat shape always start with `/`, so it cannot be
+/// mistaken for a nested one.
+///
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Snapshot {
+    Nested(Node),
+    Flat(BTreeMap<String, u64>),
+}
+
+///
+/// The reasons a snapshot cannot be loaded
+///
+#[derive(Debug)]
+pub enum SnapshotError {
+    Json(serde_json::Error),
+    BadName(String),
+    BadPath(String),
+    NameConflict(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Json(e) => write!(f, "not a snapshot: {}", e),
+            SnapshotError::BadName(name) => write!(f, "{:?} is not a valid name", name),
+            SnapshotError::BadPath(path) => write!(f, "{:?} is not an absolute path", path),
+            SnapshotError::NameConflict(path) => write!(f, "{:?} is both a file and a directory", path),
+        }
+    }
+}
+
+///
+/// Take a snapshot in the nested shape
+///
+pub fn nested(filesys: &FileSystem) -> Snapshot {
+    Snapshot::Nested(node(filesys, filesys.root))
+}
+
+///
+/// Take a snapshot in the flat shape
+///
+pub fn flat(filesys: &FileSystem) -> Snapshot {
+    let (dirs, files) = sizes(filesys);
+    let dirs = dirs.into_iter().map(|(path, total)| (format!("{}/", path.trim_end_matches('/')), total));
+    Snapshot::Flat(dirs.chain(files).collect())
+}
+
+///
+/// Save a directory and everything in it, sorted by name
+///
+fn node(filesys: &FileSystem, dir: NodeId) -> Node {
+    let directory = filesys.filesystem[dir].get();
+    let mut dirs: Vec<Node> = dir.children(&filesys.filesystem).map(|x| node(filesys, x)).collect();
+    dirs.sort_by(|x, y| x.name.cmp(&y.name));
+    Node {
+        name: directory.name.clone(),
+        total: directory.total,
+        files: directory.files.iter().map(|x| (x.name.clone(), x.size)).collect(),
+        dirs,
+    }
+}
+
+///
+/// The total of every directory and the size of every file, by path
+///
+fn sizes(filesys: &FileSystem) -> (BTreeMap<String, u64>, BTreeMap<String, u64>) {
+    let mut dirs: BTreeMap<String, u64> = BTreeMap::new();
+    let mut files: BTreeMap<String, u64> = BTreeMap::new();
+    for dir in filesys.root.descendants(&filesys.filesystem) {
+        let directory = filesys.filesystem[dir].get();
+        let dir_path = path(filesys, dir);
+        for file in &directory.files {
+            files.insert(format!("{}/{}", dir_path.trim_end_matches('/'), file.name), file.size);
+        }
+        dirs.insert(dir_path, directory.total);
+    }
+    (dirs, files)
+}
+
+///
+/// Read a snapshot of either shape back into a FileSystem
+///
+pub fn load(json: &str) -> Result<FileSystem, SnapshotError> {
+    let mut filesys = FileSystem::new();
+    match serde_json::from_str(json).map_err(SnapshotError::Json)? {
+        Snapshot::Nested(root) => {
+            let top = filesys.root;
+            restore(&mut filesys, top, &root, "")?;
+        }
+        Snapshot::Flat(paths) => {
+            for (path, size) in &paths {
+                let parts: Vec<&str> = match path.strip_prefix('/') {
+                    Some(rest) => rest.strip_suffix('/').unwrap_or(rest).split('/').collect(),
+                    None => return Err(SnapshotError::BadPath(path.clone())),
+                };
+                let is_dir = path.ends_with('/');
+                if is_dir && parts == [""] {
+                    continue;
+                }
+                let (last, parents) = parts.split_last().expect("split always gives one part");
+                let mut dir = filesys.root;
+                for part in parents {
+                    dir = make_dir(&mut filesys, dir, part, path)?;
+                }
+                match is_dir {
+                    true => make_dir(&mut filesys, dir, last, path).map(|_| ())?,
+                    false => make_file(&mut filesys, dir, File { name: last.to_string(), size: *size }, path)?,
+                }
+            }
+        }
+    }
+    filesys.cwd = filesys.root;
+    Ok(filesys)
+}
+
+///
+/// Rebuild a saved directory inside another one
+///
+fn restore(filesys: &mut FileSystem, dir: NodeId, node: &Node, dir_path: &str) -> Result<(), SnapshotError> {
+    for (name, size) in &node.files {
+        let file = File { name: name.clone(), size: *size };
+        make_file(filesys, dir, file, &format!("{}/{}", dir_path, name))?;
+    }
+    for child in &node.dirs {
+        let child_path = format!("{}/{}", dir_path, child.name);
+        let id = make_dir(filesys, dir, &child.name, &child_path)?;
+        restore(filesys, id, child, &child_path)?;
+    }
+    Ok(())
+}
+
+///
+/// Check that a name can be part of a path
+///
+fn check_name(name: &str) -> Result<(), SnapshotError> {
+    match name.is_empty() || name == "." || name == ".." || name.contains('/') {
+        true => Err(SnapshotError::BadName(name.to_string())),
+        false => Ok(()),
+    }
+}
+
+///
+/// Make a directory, or find it if it is already there
+///
+fn make_dir(filesys: &mut FileSystem, dir: NodeId, name: &str, path: &str) -> Result<NodeId, SnapshotError> {
+    check_name(name)?;
+    filesys.cwd = dir;
+    filesys.mkdir(Directory::new(name)).map_err(|e| conflict(e, path))?;
+    Ok(filesys.child(dir, name).expect("directory was just made"))
+}
+
+///
+/// Make a file, keeping the directory totals up to date
+///
+fn make_file(filesys: &mut FileSystem, dir: NodeId, file: File, path: &str) -> Result<(), SnapshotError> {
+    check_name(&file.name)?;
+    filesys.cwd = dir;
+    filesys.touch(file).map_err(|e| conflict(e, path))
+}
+
+///
+/// Turn a name conflict from the FileSystem into one with the whole path
+///
+fn conflict(error: ShellErrorKind, path: &str) -> SnapshotError {
+    match error {
+        ShellErrorKind::NameConflict(_) => SnapshotError::NameConflict(path.to_string()),
+        other => unreachable!("mkdir and touch only fail on a name conflict, not {:?}", other),
+    }
+}
+
+///
+/// Something added, removed or resized between two filesystems
+///
+#[derive(Debug, PartialEq, Eq)]
+pub enum Change {
+    Added { path: String, dir: bool, size: u64 },
+    Removed { path: String, dir: bool, size: u64 },
+    Resized { path: String, old: u64, new: u64 },
+}
+
+///
+/// The differences between two filesystems, and how much each directory total changed by
+///
+#[derive(Debug)]
+pub struct Diff {
+    pub changes: Vec<Change>,
+    pub deltas: Vec<(String, i128)>,
+}
+
+///
+/// Compare two filesystems. A directory that was added or removed is listed once, not along with
+/// everything in it.
+///
+pub fn diff(old: &FileSystem, new: &FileSystem) -> Diff {
+    let (old_dirs, old_files) = sizes(old);
+    let (new_dirs, new_files) = sizes(new);
+    let added_dirs: BTreeSet<&str> = new_dirs.keys().filter(|x| !old_dirs.contains_key(*x)).map(|x| x.as_str()).collect();
+    let removed_dirs: BTreeSet<&str> = old_dirs.keys().filter(|x| !new_dirs.contains_key(*x)).map(|x| x.as_str()).collect();
+
+    let mut changes: Vec<(String, Change)> = vec![];
+    for (path, size) in &new_dirs {
+        if added_dirs.contains(path.as_str()) && !within(&added_dirs, path) {
+            changes.push((path.clone(), Change::Added { path: path.clone(), dir: true, size: *size }));
+        }
+    }
+    for (path, size) in &old_dirs {
+        if removed_dirs.contains(path.as_str()) && !within(&removed_dirs, path) {
+            changes.push((path.clone(), Change::Removed { path: path.clone(), dir: true, size: *size }));
+        }
+    }
+    for (path, &size) in &new_files {
+        match old_files.get(path) {
+            None if !within(&added_dirs, path) => {
+                changes.push((path.clone(), Change::Added { path: path.clone(), dir: false, size }));
+            }
+            Some(&old) if old != size => {
+                changes.push((path.clone(), Change::Resized { path: path.clone(), old, new: size }));
+            }
+            _ => {}
+        }
+    }
+    for (path, &size) in &old_files {
+        if !new_files.contains_key(path) && !within(&removed_dirs, path) {
+            changes.push((path.clone(), Change::Removed { path: path.clone(), dir: false, size }));
+        }
+    }
+    changes.sort_by(|x, y| x.0.cmp(&y.0));
+
+    let paths: BTreeSet<&String> = old_dirs.keys().chain(new_dirs.keys()).collect();
+    let deltas = paths.into_iter()
+        .map(|x| {
+            let before = old_dirs.get(x).copied().unwrap_or(0) as i128;
+            (x.clone(), new_dirs.get(x).copied().unwrap_or(0) as i128 - before)
+        })
+        .filter(|x| x.1 != 0)
+        .collect();
+    Diff { changes: changes.into_iter().map(|x| x.1).collect(), deltas }
+}
+
+///
+/// Whether a path is somewhere inside one of the directories
+///
+fn within(dirs: &BTreeSet<&str>, path: &str) -> bool {
+    let mut parent = path;
+    while let Some(i) = parent.rfind('/') {
+        parent = &parent[..i];
+        if parent.is_empty() {
+            return false;
+        }
+        if dirs.contains(parent) {
+            return true;
+        }
+    }
+    false
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.changes.is_empty() {
+            return write!(f, "no differences");
+        }
+        let kind = |dir: bool| if dir { "dir" } else { "file" };
+        for change in &self.changes {
+            match change {
+                Change::Added { path, dir, size } => writeln!(f, "+ {} ({}, size={})", path, kind(*dir), size)?,
+                Change::Removed { path, dir, size } => writeln!(f, "- {} ({}, size={})", path, kind(*dir), size)?,
+                Change::Resized { path, old, new } => {
+                    writeln!(f, "~ {} (file, size={} -> {}, {:+})", path, old, new, *new as i128 - *old as i128)?
+                }
+            }
+        }
+        writeln!(f)?;
+        write!(f, "directory totals:")?;
+        for (path, delta) in &self.deltas {
+            write!(f, "\n{:>+12}  {}", delta, path)?;
+        }
+        Ok(())
+    }
+}